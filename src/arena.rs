@@ -0,0 +1,329 @@
+pub mod arena {
+    use std::cell::RefCell;
+
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use crate::game::game::*;
+    use crate::replay::replay::*;
+    use crate::snake::snake::*;
+    use crate::strategy::strategy::*;
+    use crate::STALL_AFTER;
+
+    // One of the snakes sharing the board and whatever is steering it
+    pub struct Player {
+        pub mode: Mode,
+        pub snake: Snake,
+        pub strategy: Box<dyn Strategy>,
+        pub score: i32,
+        // The move the snake went out on
+        pub out_at: Option<usize>,
+        // Rounds won in the current match
+        pub wins: usize,
+    }
+
+    // Several snakes on one board, all moving at the same time.
+    // A snake is out when it runs into a wall, the edge, itself or the body of another snake.
+    // When two heads meet the longer snake survives, and both are out when they are the same length.
+    pub struct Arena {
+        pub grid: Grid,
+        pub players: Vec<Player>,
+        pub food: Food,
+        start_len: usize,
+        pub frame_count: usize,
+        pub seed: u64,
+        rng: StdRng,
+    }
+
+    impl Arena {
+        pub fn new(grid: Grid, modes: &[Mode], options: &StrategyOptions, start_len: usize, food_count: usize, seed: u64) -> Result<Self, String> {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let snakes: Vec<Snake> = Self::starts(&grid, modes.len(), start_len)?.into_iter()
+                .map(|body| Snake::from_body(&grid, body))
+                .collect();
+            let food = Food::new(&grid, &snakes.iter().collect::<Vec<&Snake>>(), food_count, Vec::new(), &mut rng);
+
+            let mut arena = Self {
+                grid,
+                players: Vec::new(),
+                food,
+                start_len,
+                frame_count: 0,
+                seed,
+                rng,
+            };
+
+            for (mode, snake) in modes.iter().zip(snakes) {
+                arena.players.push(Player {
+                    mode: *mode,
+                    snake,
+                    strategy: Box::new(Human::new()),
+                    score: 0,
+                    out_at: None,
+                    wins: 0,
+                });
+            }
+
+            for i in 0..arena.players.len() {
+                let view = arena.view(i, false);
+                arena.players[i].strategy = create_strategy(arena.players[i].mode, options, &view)
+                    .map_err(|err| format!("Snake {} ({}): {}", i + 1, arena.players[i].mode.name(), err))?;
+                arena.rng = view.rng.into_inner();
+            }
+
+            Ok(arena)
+        }
+
+        // Every snake gets its own row, spread evenly down the board. Every other snake starts on the right heading left.
+        fn starts(grid: &Grid, count: usize, start_len: usize) -> Result<Vec<Vec<Pos>>, String> {
+            if grid.height <= count {
+                return Err(format!("A board {} cells high only has room for {} snakes", grid.height, grid.height.saturating_sub(1)));
+            }
+            if grid.width < start_len {
+                return Err(format!("A board {} cells wide has no room for snakes of {} cells", grid.width, start_len));
+            }
+
+            let mut starts = Vec::new();
+            for i in 0..count {
+                let y = (i + 1) * grid.height / (count + 1);
+                let body: Vec<Pos> = match i % 2 {
+                    0 => (0..start_len).rev().map(|x| Pos::new(x, y)).collect(),
+                    _ => (grid.width - start_len..grid.width).map(|x| Pos::new(x, y)).collect(),
+                };
+
+                if body.iter().any(|pos| grid.is_wall(pos)) {
+                    return Err(format!("Snake {} can't start on row {}, there is a wall in the way", i + 1, y));
+                }
+
+                starts.push(body);
+            }
+
+            Ok(starts)
+        }
+
+        // Start a new round with a seed drawn from the current one
+        pub fn reset(&mut self) {
+            self.seed = self.rng.gen();
+            self.rng = StdRng::seed_from_u64(self.seed);
+
+            let starts = Self::starts(&self.grid, self.players.len(), self.start_len).expect("The snakes fit when the arena was made");
+            for (player, body) in self.players.iter_mut().zip(starts) {
+                player.snake = Snake::from_body(&self.grid, body);
+                player.score = 0;
+                player.out_at = None;
+            }
+
+            let snakes: Vec<&Snake> = self.players.iter().map(|player| &player.snake).collect();
+            self.food.reset(&self.grid, &snakes, &mut self.rng);
+            self.frame_count = 0;
+
+            for i in 0..self.players.len() {
+                let view = self.view(i, false);
+                self.players[i].strategy.reset(&view);
+                self.rng = view.rng.into_inner();
+            }
+        }
+
+        // Start over with nobody having won a round
+        pub fn new_match(&mut self) {
+            for player in self.players.iter_mut() {
+                player.wins = 0;
+            }
+
+            self.reset();
+        }
+
+        // Give the round to its winner, if there was one
+        pub fn end_round(&mut self) -> Option<usize> {
+            let winner = self.winner();
+
+            if let Some(i) = winner {
+                self.players[i].wins += 1;
+            }
+
+            winner
+        }
+
+        // The first snake to win enough rounds takes the match
+        pub fn champion(&self, rounds: usize) -> Option<usize> {
+            self.players.iter().position(|player| player.wins >= rounds)
+        }
+
+        pub fn alive(&self) -> usize {
+            self.players.iter().filter(|player| !player.snake.game_over).count()
+        }
+
+        // The round is over once at most one snake is left
+        pub fn game_ended(&self) -> bool {
+            self.alive() <= 1
+        }
+
+        // The last snake standing, or the highest score when the round was cut short
+        pub fn winner(&self) -> Option<usize> {
+            let alive: Vec<usize> = (0..self.players.len()).filter(|i| !self.players[*i].snake.game_over).collect();
+            let candidates = match alive.len() {
+                0 => return None,
+                1 => return Some(alive[0]),
+                _ => alive,
+            };
+
+            let best = candidates.iter().map(|i| self.players[*i].score).max()?;
+            let leaders: Vec<usize> = candidates.into_iter().filter(|i| self.players[*i].score == best).collect();
+
+            match leaders[..] {
+                [leader] => Some(leader),
+                _ => None,
+            }
+        }
+
+        // The game as one snake sees it. The other snakes are walls, so every strategy already steers around them.
+        // The score counts everything eaten on the board, so strategies search again whenever the food moves.
+        // Hamiltonian cycles can't be made on a board with walls, so they are planned on the board without the others.
+        fn view(&self, index: usize, others_as_walls: bool) -> Game {
+            let mut grid = self.grid.clone();
+
+            if others_as_walls {
+                for (i, player) in self.players.iter().enumerate() {
+                    if i != index && !player.snake.game_over {
+                        player.snake.body.iter().for_each(|pos| grid.set_wall(pos, true));
+                    }
+                }
+            }
+
+            let snake = self.players[index].snake.clone();
+            let others = self.players.iter().enumerate()
+                .filter(|(i, player)| *i != index && !player.snake.game_over)
+                .map(|(_, player)| player.snake.clone())
+                .collect();
+
+            Game {
+                replay: Replay::new(grid.clone(), self.seed, snake.body.clone(), self.food.pieces.clone()),
+                grid,
+                start: snake.body.clone(),
+                snake,
+                others,
+                food: self.food.clone(),
+                score: self.players.iter().map(|player| player.score).sum(),
+                frame_count: self.frame_count,
+                seed: self.seed,
+                rng: RefCell::new(self.rng.clone()),
+            }
+        }
+
+        // Pass a direction key on to the snake steered by that player, the first play snake is player 0
+        pub fn on_key(&mut self, human: usize, dir: Direction) {
+            if let Some(player) = self.players.iter_mut().filter(|player| player.mode == Mode::Play).nth(human) {
+                player.strategy.on_key(dir);
+            }
+        }
+
+        // Advance every snake still in the round by a single move
+        pub fn step(&mut self) {
+            if self.game_ended() {
+                return;
+            }
+
+            // Every snake picks its move on the board as it was before anyone moved
+            for i in 0..self.players.len() {
+                if self.players[i].snake.game_over {
+                    continue;
+                }
+
+                let view = self.view(i, true);
+                let dir = self.players[i].strategy.next_move(&view);
+                self.rng = view.rng.into_inner();
+                self.players[i].snake.set_next_direction(dir);
+            }
+
+            let heads: Vec<Option<Pos>> = self.players.iter().map(|player| match player.snake.game_over {
+                true => None,
+                false => player.snake.head().adjacent(&player.snake.next_direction(), &self.grid),
+            }).collect();
+
+            let mut out = vec![false; self.players.len()];
+            for (i, player) in self.players.iter().enumerate() {
+                if player.snake.game_over {
+                    continue;
+                }
+
+                let next = match heads[i] {
+                    Some(next) => next,
+                    None => { out[i] = true; continue; }
+                };
+
+                for (j, other) in self.players.iter().enumerate() {
+                    if other.snake.game_over {
+                        continue;
+                    }
+
+                    let body = match i == j {
+                        true => &other.snake.body[1..],
+                        false => &other.snake.body[..],
+                    };
+
+                    if body.contains(&next) || (i != j && heads[j] == Some(next) && player.snake.body.len() <= other.snake.body.len()) {
+                        out[i] = true;
+                    }
+                }
+            }
+
+            for (i, player) in self.players.iter_mut().enumerate() {
+                if out[i] {
+                    player.snake.game_over = true;
+                    player.out_at = Some(self.frame_count + 1);
+                }
+                else if let Some(next) = heads[i] {
+                    let dir = player.snake.next_direction();
+                    player.snake.follow(&self.grid, &self.food.pieces, &[dir]);
+
+                    if self.food.pieces.contains(&next) {
+                        player.score += 1;
+                    }
+                }
+            }
+
+            // Eaten food respawns once every snake has moved, so it never lands under one of them
+            for i in 0..self.players.len() {
+                let head = self.players[i].snake.head();
+
+                if let Some(index) = self.food.pieces.iter().position(|pos| *pos == head).filter(|_| heads[i].is_some() && !out[i]) {
+                    let snakes: Vec<&Snake> = self.players.iter().map(|player| &player.snake).collect();
+                    self.food.respawn(index, &self.grid, &snakes, &mut self.rng);
+                }
+            }
+
+            self.frame_count += 1;
+
+            // Tell the snakes that just went out, and the last one left once the round is over
+            let ended = self.game_ended();
+            for (i, went_out) in out.into_iter().enumerate() {
+                if went_out || (ended && !self.players[i].snake.game_over) {
+                    let view = self.view(i, true);
+                    self.players[i].strategy.end(&view);
+                }
+            }
+        }
+    }
+
+    // Play a round to the end, giving up when nobody has eaten for a long time
+    pub fn run_arena(arena: &mut Arena) -> bool {
+        let stall_limit = arena.grid.width * arena.grid.height * STALL_AFTER;
+
+        let mut last_eaten = 0;
+        let mut eaten: i32 = 0;
+
+        while !arena.game_ended() {
+            arena.step();
+
+            let total = arena.players.iter().map(|player| player.score).sum();
+            if total != eaten {
+                eaten = total;
+                last_eaten = arena.frame_count;
+            }
+            else if arena.frame_count - last_eaten > stall_limit {
+                return true;
+            }
+        }
+
+        false
+    }
+}
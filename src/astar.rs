@@ -1,229 +1,318 @@
 pub mod astar {
-    use raylib::prelude::*;
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
 
+    use crate::game::game::*;
     use crate::snake::snake::*;
-    use crate::{GRID_WIDTH, GRID_HEIGHT, CELL_SIZE, CELL_SIZE_I, SEARCH_EVERY};
+    use crate::strategy::strategy::*;
 
     #[derive(Clone, Copy)]
-    pub struct Node {
-        pos: Pos,
-        parent: Pos,
-        f: i32,
+    struct Node {
+        parent: Option<Pos>,
         g: i32,
-        h: i32,
+        closed: bool,
+        // The search that last touched this node, anything older is stale
+        search: u32,
     }
 
     pub struct AStar {
         pub path: Vec<Direction>,
         path_index: usize,
-        end: Option<Node>,
-        end_pos: Pos,
-        nodes: Vec<Vec<Node>>,
-        open: Vec<Pos>,
-        closed: Vec<Vec<bool>>,
-        random_dir: bool
+        // Reused between searches, indexed by y * width + x
+        nodes: Vec<Node>,
+        // The search that stamped a cell as part of the body, and the first step the cell is free again
+        blocked: Vec<(u32, i32)>,
+        search_id: u32,
+        open: BinaryHeap<Reverse<(i32, i32, usize)>>,
+        fallback: Fallback,
+        // Only take paths to the food that leave a way back to the tail
+        safe: bool,
+        // Moves to wait before searching again when there was no path
+        search_every: usize,
+        score: i32
     }
 
     impl AStar {
-        pub fn new(random_dir: bool) -> Self {
+        pub fn new(fallback: Fallback, safe: bool, search_every: usize) -> Self {
             Self {
                 path: Vec::new(),
                 path_index: 0,
-                end: None,
-                end_pos: Pos::new(GRID_WIDTH, GRID_HEIGHT),
                 nodes: Vec::new(),
-                open: Vec::new(),
-                closed: Vec::new(),
-                random_dir
+                blocked: Vec::new(),
+                search_id: 0,
+                open: BinaryHeap::new(),
+                fallback,
+                safe,
+                search_every,
+                score: 0
             }
         }
 
-        pub fn reset(&mut self) {
-            self.path = Vec::new();
-            self.path_index = 0;
-            self.end = None;
-            self.end_pos = Pos::new(GRID_WIDTH, GRID_HEIGHT);
-            self.nodes = Vec::new();
-            self.open = Vec::new();
-            self.closed = Vec::new();
+        pub fn get_next_move(&self) -> Direction {
+            self.path[self.path_index]
         }
 
-        pub fn update(&mut self, snake: &mut Snake, food: &mut Food, frame_count: &usize, score: &mut i32) {
-            let score_before = *score;
-            let path_found = self.path_found();
+        pub fn path_found(&self) -> bool {
+            self.path_index < self.path.len()
+        }
 
-            if path_found {
-                snake.set_next_direction(self.get_next_move());
-                self.path_index += 1;
-            }
-            else if !path_found && snake.would_collide() {
-                if self.random_dir {
-                    let dir = snake.get_random_free_dir();
-                    snake.set_next_direction(dir);
-                }
-                else {
-                    let dir = snake.get_dir_of_free_space();
-                    snake.set_next_direction(dir);
-                }
+        // Other snakes can move onto the path, which shows up as a wall in the way of the next step
+        fn path_blocked(&self, game: &Game) -> bool {
+            self.path_found() && game.snake.head().adjacent(&self.get_next_move(), &game.grid).is_none()
+        }
+
+        // The distance to the closest end, so the search finds the nearest one it can reach
+        fn calculate_h_value(&self, grid: &Grid, ends: &[Pos], pos: &Pos) -> i32 {
+            ends.iter().map(|end| pos.distance(end, grid) as i32).min().unwrap_or(0)
+        }
+
+        // Get the node for this search, clearing it if it was left over from an earlier one
+        fn node(&mut self, idx: usize) -> &mut Node {
+            let node = &mut self.nodes[idx];
+
+            if node.search != self.search_id {
+                *node = Node {
+                    parent: None,
+                    g: i32::MAX,
+                    closed: false,
+                    search: self.search_id,
+                };
             }
 
-            snake.update(food, score);
-    
-            if score_before - *score != 0 || (!path_found && frame_count % SEARCH_EVERY == 0) {
-                self.search(&snake, &food);
-                self.path_index = 0;
+            node
+        }
+
+        fn get_path(&mut self, grid: &Grid, end: &Pos) -> Vec<Direction> {
+            let mut path = Vec::new();
+            let mut cur = *end;
+
+            while let Some(parent) = self.node(cur.y * grid.width + cur.x).parent {
+                path.push(parent.get_dir_to(&cur, grid).expect("Parents are always next to their children"));
+                cur = parent;
             }
+
+            path.reverse();
+            path
         }
 
-        pub fn draw_path(&self, draw: &mut RaylibDrawHandle, snake: &Snake) {
-            if self.path_found() {
-                let mut cur = snake.head();
-                for dir in self.path[self.path_index..].iter() {
-                    cur = cur.transform(dir);
-                    let x = (cur.x * CELL_SIZE) as i32;
-                    let y = (cur.y * CELL_SIZE) as i32;
-                    draw.draw_rectangle(x, y, CELL_SIZE_I, CELL_SIZE_I, Color::GRAY);
+        // The path to whichever end is closest. The obstacles are a body following the head, ordered from the neck to the tail.
+        pub fn shortest_path(&mut self, grid: &Grid, start: &Pos, ends: &[Pos], obstacles: &[Pos]) -> Option<Vec<Direction>> {
+            if !grid.in_bounds(start.x, start.y) || ends.is_empty() || ends.contains(start) {
+                return None
+            }
+
+            let size = grid.width * grid.height;
+            if self.nodes.len() != size {
+                self.nodes = vec![Node { parent: None, g: i32::MAX, closed: false, search: 0 }; size];
+                self.blocked = vec![(0, 0); size];
+                self.search_id = 0;
+            }
+
+            self.search_id = self.search_id.wrapping_add(1);
+            if self.search_id == 0 {
+                // Every stamp would look current after wrapping around, so start over
+                self.nodes.iter_mut().for_each(|node| node.search = 0);
+                self.blocked.iter_mut().for_each(|(search, _)| *search = 0);
+                self.search_id = 1;
+            }
+
+            // The body moves along while the head follows the path, so segment i of n leaves its cell on step n - i.
+            // The tail only moves after the head has, so the head can't step there on that same move and the cell is free from step n - i + 1.
+            // When a segment shows up more than once, because the snake just grew, the cell stays taken the longest.
+            for (i, pos) in obstacles.iter().enumerate() {
+                let free_at = (obstacles.len() - i + 1) as i32;
+                let blocked = &mut self.blocked[pos.y * grid.width + pos.x];
+
+                if blocked.0 != self.search_id || blocked.1 < free_at {
+                    *blocked = (self.search_id, free_at);
                 }
             }
-        }
 
-        pub fn get_next_move(&self) -> Direction {
-            self.path[self.path_index]
-        }
+            let start_idx = start.y * grid.width + start.x;
+            self.node(start_idx).g = 0;
 
-        pub fn path_found(&self) -> bool {
-            match self.end {
-                Some(_) => true,
-                None => false
+            self.open.clear();
+            self.open.push(Reverse((self.calculate_h_value(grid, ends, start), 0, start_idx)));
+
+            while let Some(Reverse((_, _, idx))) = self.open.pop() {
+                if self.node(idx).closed {
+                    continue;
+                }
+                self.node(idx).closed = true;
+
+                let cur = Pos::new(idx % grid.width, idx / grid.width);
+                if ends.contains(&cur) {
+                    return Some(self.get_path(grid, &cur));
+                }
+
+                let new_g = self.node(idx).g + 1;
+
+                for next in cur.get_neighbors(grid) {
+                    let next_idx = next.y * grid.width + next.x;
+
+                    let (search, free_at) = self.blocked[next_idx];
+                    if search == self.search_id && new_g < free_at {
+                        continue;
+                    }
+
+                    let node = self.node(next_idx);
+                    if !node.closed && new_g < node.g {
+                        node.g = new_g;
+                        node.parent = Some(cur);
+
+                        let new_h = self.calculate_h_value(grid, ends, &next);
+                        self.open.push(Reverse((new_g + new_h, new_h, next_idx)));
+                    }
+                }
             }
-        }
 
-        fn is_unblocked(&self, obstacles: &Vec<Pos>, x: usize, y: usize) -> bool {
-            !obstacles.contains(&Pos::new(x, y))
-        }
-    
-        fn is_destination(&self, end: &Pos, x: usize, y: usize) -> bool {
-            end.x == x && end.y == y
+            None
         }
-    
-        fn calculate_h_value(&self, end: &Pos, x: usize, y: usize) -> i32 {
-            (x as i32 - end.x as i32).abs() + (y as i32 - end.y as i32).abs()
+
+        // Whether a cell on the grid can be stepped on after this many steps of the last search
+        fn is_free(&self, grid: &Grid, pos: &Pos, step: usize, visited: &[bool]) -> bool {
+            let idx = pos.y * grid.width + pos.x;
+            let (search, free_at) = self.blocked[idx];
+
+            !visited[idx] && (search != self.search_id || free_at <= step as i32)
         }
-    
-        fn test_neighbors(&mut self, end: &Pos, obstacles: &Vec<Pos>, x: usize, y: usize, check_x: bool) -> bool {
-            let offsets = vec![-1 as i32, 1];
 
-            // ====================
-            // Test offsets
-            // ====================
-            for offset in offsets.iter() {
-                let x_mult = check_x as i32;
-                let y_mult = 1 - x_mult;
+        // Stretch the shortest path by swapping single steps for three step detours through the free cells next to it.
+        // A detour only pushes the rest of the path further back in time, so the body has moved out of the way even more.
+        pub fn longest_path(&mut self, grid: &Grid, start: &Pos, ends: &[Pos], obstacles: &[Pos]) -> Option<Vec<Direction>> {
+            let path = self.shortest_path(grid, start, ends, obstacles)?;
+
+            let mut cells = vec![*start];
+            for dir in path.iter() {
+                let next = cells[cells.len() - 1].adjacent(dir, grid).expect("The path stays on the grid");
+                cells.push(next);
+            }
+
+            let mut visited = vec![false; grid.width * grid.height];
+            for pos in cells.iter() {
+                visited[pos.y * grid.width + pos.x] = true;
+            }
 
-                let new_x = (x as i32 + (*offset * x_mult)) as usize;
-                let new_y = (y as i32 + (*offset * y_mult)) as usize;
+            let mut i = 0;
+            while i + 1 < cells.len() {
+                let from = cells[i];
+                let to = cells[i + 1];
+                let dir = from.get_dir_to(&to, grid).expect("The path only moves between neighbors");
 
-                if in_bounds(new_x, new_y) {
-                    if self.is_destination(end, new_x, new_y) {
-                        self.nodes[new_x][new_y].parent.x = x;
-                        self.nodes[new_x][new_y].parent.y = y;
-    
-                        self.end = Some(self.nodes[new_x][new_y]);
-                        self.end_pos = Pos::new(new_x, new_y);
+                let detour = dir.perpendicular().iter().find_map(|side| {
+                    let a = from.adjacent(side, grid)?;
+                    let b = to.adjacent(side, grid)?;
 
-                        return true
+                    // Passing another end on the way would end the path early, like eating a different piece of food
+                    let free = self.is_free(grid, &a, i + 1, &visited) && self.is_free(grid, &b, i + 2, &visited);
+
+                    match free && !ends.contains(&a) && !ends.contains(&b) {
+                        true => Some((a, b)),
+                        false => None,
                     }
-                    else if !self.closed[new_x][new_y] && self.is_unblocked(&obstacles, new_x, new_y) {
-                        let new_g = self.nodes[x][y].g + 1;
-                        let new_h = self.calculate_h_value(end, new_x, new_y);
-                        let new_f = new_g + new_h;
-
-                        if self.nodes[new_x][new_y].f == i32::MAX || self.nodes[new_x][new_y].f < new_f {
-                            self.open.push(Pos::new(new_x, new_y));
-    
-                            self.nodes[new_x][new_y].g = new_g;
-                            self.nodes[new_x][new_y].h = new_h;
-                            self.nodes[new_x][new_y].f = new_f;
-                            self.nodes[new_x][new_y].parent.x = x;
-                            self.nodes[new_x][new_y].parent.y = y;
-                        }
+                });
+
+                // The first step of a detour can be stretched again, so only move on once nothing fits
+                match detour {
+                    Some((a, b)) => {
+                        visited[a.y * grid.width + a.x] = true;
+                        visited[b.y * grid.width + b.x] = true;
+                        cells.insert(i + 1, a);
+                        cells.insert(i + 2, b);
                     }
+                    None => i += 1,
                 }
             }
 
-            return false
+            Some(cells.windows(2).map(|pair| pair[0].get_dir_to(&pair[1], grid).expect("The path only moves between neighbors")).collect())
         }
 
-        fn test_pos(&mut self, end: &Pos, obstacles: &Vec<Pos>, x: usize, y: usize) -> bool {
-            self.test_neighbors(end, obstacles, x, y, true) || self.test_neighbors(end, obstacles, x, y, false)
+        // Whether the snake could still reach its tail after following the path
+        pub fn leaves_way_out(&mut self, game: &Game, path: &[Direction]) -> bool {
+            let mut snake = game.snake.clone();
+            snake.follow(&game.grid, &game.food.pieces, path);
+
+            snake.body.len() == game.grid.open_cells()
+                || self.shortest_path(&game.grid, &snake.head(), &[snake.tail()], &snake.exclude_head()).is_some()
         }
 
-        fn get_path(&mut self, node: &Node) {
-            if node.parent.x == GRID_WIDTH || node.parent.y == GRID_HEIGHT {
-                return;
-            }
+        pub fn search(&mut self, game: &Game) {
+            let path = self.shortest_path(&game.grid, &game.snake.head(), &game.food.pieces, &game.snake.exclude_head());
 
-            let parent = self.nodes[node.parent.x as usize][node.parent.y as usize];
-            self.get_path(&parent);
-            self.path.push(parent.pos.get_dir_to(&node.pos).unwrap());
+            self.path = match path {
+                Some(path) if !self.safe || self.leaves_way_out(game, &path) => path,
+                _ => Vec::new(),
+            };
         }
 
-        pub fn shortest_path(&mut self, start: &Pos, end: &Pos, obstacles: &Vec<Pos>) {
-            self.path = Vec::new();
+        // Stall by taking the first step of the longest path to the tail, which keeps the tail reachable.
+        // Eating on the way leaves the tail where it is for a move, which can close the loop in front of the head,
+        // so the food is gone around when that first step would cut the snake off.
+        pub fn chase_tail(&mut self, game: &Game) -> Option<Direction> {
+            let snake = &game.snake;
+            let path = self.longest_path(&game.grid, &snake.head(), &[snake.tail()], &snake.exclude_head())?;
+
+            if self.leaves_way_out(game, &path[..1]) {
+                return path.first().copied();
+            }
+
+            let around_food: Vec<Pos> = game.food.pieces.iter().chain(snake.exclude_head().iter()).copied().collect();
+            self.longest_path(&game.grid, &snake.head(), &[snake.tail()], &around_food)
+                .unwrap_or(path)
+                .first()
+                .copied()
+        }
 
-            self.closed = vec![vec![false; GRID_HEIGHT]; GRID_WIDTH];
-            self.nodes = Vec::new();
-
-            self.end = None;
-    
-            if start.x == GRID_WIDTH || start.y == GRID_HEIGHT || start == end {
-                return
-            }
-            
-            for x in 0..GRID_WIDTH {
-                self.nodes.push(Vec::new());
-                for y in 0..GRID_HEIGHT {
-                    self.nodes[x].push(Node {
-                        pos: Pos::new(x, y),
-                        parent: Pos::new(GRID_WIDTH, GRID_HEIGHT),
-                        f: i32::MAX,
-                        g: i32::MAX,
-                        h: i32::MAX
-                    })
+        fn fallback(&mut self, game: &Game) -> Direction {
+            if self.safe || self.fallback == Fallback::LongestPath {
+                if let Some(dir) = self.chase_tail(game) {
+                    return dir;
                 }
             }
-    
-            self.open = Vec::new();
-            self.open.push(*start);
-
-            self.nodes[start.x][start.y].f = 0;
-            self.nodes[start.x][start.y].g = 0;
-            self.nodes[start.x][start.y].h = 0;
-            self.nodes[start.x][start.y].parent.x = GRID_WIDTH;
-            self.nodes[start.x][start.y].parent.y = GRID_HEIGHT;
-
-            let mut found = false;
-    
-            while !found && !self.open.is_empty() {
-                let cur_pos = self.open.remove(0);
-    
-                let x = cur_pos.x as usize;
-                let y = cur_pos.y as usize;
-    
-                self.closed[x][y] = true;
-    
-                found = self.test_pos(end, &obstacles, x, y);
-            }
-
-            match self.end {
-                Some(node) => { self.get_path(&node); },
-                None => {}
-            };
+
+            if !game.snake.would_collide(&game.grid) {
+                game.snake.direction()
+            }
+            else if self.fallback == Fallback::RandomDir {
+                game.snake.get_random_free_dir(&game.grid, &mut *game.rng.borrow_mut())
+            }
+            else {
+                game.snake.get_dir_of_free_space(&game.grid)
+            }
         }
+    }
 
-        pub fn search(&mut self, snake: &Snake, food: &Food) {
-            self.shortest_path(&snake.head(), &food.pos, &snake.exclude_head())
+    impl Strategy for AStar {
+        fn next_move(&mut self, game: &Game) -> Direction {
+            // Search again when the food has moved, when the last search came up empty or when something moved into the way
+            if game.score != self.score || (!self.path_found() && game.frame_count.is_multiple_of(self.search_every)) || self.path_blocked(game) {
+                self.search(game);
+                self.path_index = 0;
+                self.score = game.score;
+            }
+
+            if self.path_found() {
+                let dir = self.get_next_move();
+                self.path_index += 1;
+
+                dir
+            }
+            else {
+                self.fallback(game)
+            }
+        }
+
+        fn reset(&mut self, game: &Game) {
+            self.path = Vec::new();
+            self.path_index = 0;
+            self.score = game.score;
+        }
+
+        fn planned_path(&self) -> &[Direction] {
+            match self.path_found() {
+                true => &self.path[self.path_index..],
+                false => &[],
+            }
         }
     }
-    
-}
\ No newline at end of file
+}
@@ -0,0 +1,484 @@
+pub mod battlesnake {
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use crate::game::game::*;
+    use crate::http::http::*;
+    use crate::json::json::*;
+    use crate::settings::settings::Rgb;
+    use crate::snake::snake::*;
+    use crate::strategy::strategy::*;
+    use crate::{SERVE_FORGET_AFTER, MAX_BOARD};
+
+    // Battlesnake counts y up from the bottom of the board, the grid counts down from the top
+    pub fn pos_to_json(pos: &Pos, grid: &Grid) -> Json {
+        Json::object(vec![
+            ("x", Json::Number(pos.x as f64)),
+            ("y", Json::Number((grid.height - 1 - pos.y) as f64)),
+        ])
+    }
+
+    pub fn pos_from_json(json: &Json, grid: &Grid) -> Result<Pos, String> {
+        let x = json.get("x").and_then(|x| x.as_usize());
+        let y = json.get("y").and_then(|y| y.as_usize());
+
+        match (x, y) {
+            (Some(x), Some(y)) if grid.in_bounds(x, y) => Ok(Pos::new(x, grid.height - 1 - y)),
+            _ => Err(format!("{} isn't a cell on the {}x{} board", json, grid.width, grid.height)),
+        }
+    }
+
+    fn positions_from_json(json: Option<&Json>, grid: &Grid) -> Result<Vec<Pos>, String> {
+        match json {
+            Some(json) => json.as_array().ok_or(format!("{} isn't a list of cells", json))?
+                .iter().map(|pos| pos_from_json(pos, grid)).collect(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // A body of at least two cells, so it is clear which way the snake is heading.
+    // Battlesnakes start with their whole body in one cell, which looks like heading right.
+    fn body_from_json(snake: &Json, grid: &Grid) -> Result<Vec<Pos>, String> {
+        let mut body = positions_from_json(snake.get("body"), grid)?;
+
+        match body.first() {
+            Some(head) if body.len() == 1 => body.push(*head),
+            Some(_) => {}
+            None => return Err("a snake has no body".to_string()),
+        }
+
+        Ok(body)
+    }
+
+    // The game a request describes, as the snake it was sent for sees it. The other snakes are walls when asked for,
+    // like in an arena, since Hamiltonian cycles are planned on the board without them.
+    pub fn game_from_json(json: &Json, seed: u64, others_as_walls: bool) -> Result<Game, String> {
+        let board = json.get("board").ok_or("the request has no board")?;
+        let size = |key: &str| board.get(key).and_then(|size| size.as_usize()).filter(|size| (2..=MAX_BOARD).contains(size))
+            .ok_or(format!("the board needs a {} between 2 and {}", key, MAX_BOARD));
+
+        let wrap = json.get("game").and_then(|game| game.get("ruleset")).and_then(|ruleset| ruleset.get("name")).and_then(|name| name.as_str()) == Some("wrapped");
+        let mut grid = Grid::new(size("width")?, size("height")?).with_wrap(wrap);
+
+        for wall in positions_from_json(board.get("walls"), &grid)? {
+            grid.set_wall(&wall, true);
+        }
+
+        let you = json.get("you").ok_or("the request has no snake to move")?;
+        let id = you.get("id").and_then(|id| id.as_str());
+        let body = body_from_json(you, &grid)?;
+
+        let mut others = Vec::new();
+        for snake in board.get("snakes").and_then(|snakes| snakes.as_array()).unwrap_or(&[]) {
+            if snake.get("id").and_then(|id| id.as_str()) != id {
+                others.push(Snake::from_body(&grid, body_from_json(snake, &grid)?));
+            }
+        }
+
+        let food = positions_from_json(board.get("food"), &grid)?;
+
+        if others_as_walls {
+            for other in others.iter() {
+                other.body.iter().for_each(|pos| grid.set_wall(pos, true));
+            }
+        }
+
+        let mut game = Game::new(grid, body, Vec::new(), 1, seed);
+        game.food.pieces = food;
+        game.others = others;
+        game.frame_count = json.get("turn").and_then(|turn| turn.as_usize()).unwrap_or(0);
+
+        Ok(game)
+    }
+
+    fn positions_to_json(positions: &[Pos], grid: &Grid) -> Json {
+        Json::Array(positions.iter().map(|pos| pos_to_json(pos, grid)).collect())
+    }
+
+    // Snakes here never starve, so their health is always full
+    fn snake_to_json(id: &str, snake: &Snake, grid: &Grid) -> Json {
+        Json::object(vec![
+            ("id", Json::String(id.to_string())),
+            ("name", Json::String(id.to_string())),
+            ("health", Json::Number(100.0)),
+            ("body", positions_to_json(&snake.body, grid)),
+            ("head", pos_to_json(&snake.head(), grid)),
+            ("length", Json::Number(snake.body.len() as f64)),
+            ("latency", Json::String("0".to_string())),
+            ("shout", Json::String(String::new())),
+        ])
+    }
+
+    // What every call to a bot is sent: the game, the turn, the board and which snake is theirs.
+    // Walls have no place in the API, so they go in an extra field that bots which don't know about them can ignore.
+    pub fn game_to_json(game: &Game, id: &str, timeout: Duration) -> Json {
+        let grid = &game.grid;
+        let you = snake_to_json("you", &game.snake, grid);
+
+        let mut snakes = vec![you.clone()];
+        for (i, other) in game.others.iter().enumerate() {
+            snakes.push(snake_to_json(&format!("snake-{}", i + 2), other, grid));
+        }
+
+        let ruleset = match grid.wrap {
+            true => "wrapped",
+            false => "standard",
+        };
+
+        Json::object(vec![
+            ("game", Json::object(vec![
+                ("id", Json::String(id.to_string())),
+                ("ruleset", Json::object(vec![
+                    ("name", Json::String(ruleset.to_string())),
+                    ("version", Json::String(env!("CARGO_PKG_VERSION").to_string())),
+                ])),
+                ("map", Json::String("standard".to_string())),
+                ("timeout", Json::Number(timeout.as_millis() as f64)),
+                ("source", Json::String("custom".to_string())),
+            ])),
+            ("turn", Json::Number(game.frame_count as f64)),
+            ("board", Json::object(vec![
+                ("height", Json::Number(grid.height as f64)),
+                ("width", Json::Number(grid.width as f64)),
+                ("food", positions_to_json(&game.food.pieces, grid)),
+                ("hazards", Json::Array(Vec::new())),
+                ("snakes", Json::Array(snakes)),
+                ("walls", positions_to_json(&game.fixed_walls(), grid)),
+            ])),
+            ("you", you),
+        ])
+    }
+
+    pub fn direction_name(dir: Direction) -> &'static str {
+        match dir {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        }
+    }
+
+    // The answer to /move, like {"move": "up", "shout": "..."}
+    fn parse_move(body: &str) -> Result<Direction, String> {
+        let json = Json::parse(body).map_err(|err| format!("the move isn't valid JSON: {}", err))?;
+
+        match json.get("move").and_then(|dir| dir.as_str()) {
+            Some("up") => Ok(Direction::Up),
+            Some("down") => Ok(Direction::Down),
+            Some("left") => Ok(Direction::Left),
+            Some("right") => Ok(Direction::Right),
+            Some(dir) => Err(format!("'{}' isn't a move, expected up, down, left or right", dir)),
+            None => Err(format!("the answer '{}' has no move", body)),
+        }
+    }
+
+    // A snake steered by a Battlesnake bot over HTTP, which gets /start before its first move, /move every turn and /end
+    // once the snake is out. A bot that doesn't answer in time keeps going the way it was, like on the Battlesnake servers.
+    pub struct HttpBot {
+        url: Url,
+        timeout: Duration,
+        // The game the bot was told about with /start, if it is still going
+        game_id: Option<String>,
+        games: usize,
+    }
+
+    impl HttpBot {
+        pub fn new(address: &str, timeout: Duration) -> Result<Self, String> {
+            Ok(Self {
+                url: address.parse().map_err(|err| format!("Invalid bot address: {}", err))?,
+                timeout,
+                game_id: None,
+                games: 0,
+            })
+        }
+
+        fn call(&self, path: &str, game: &Game) -> Result<String, String> {
+            let id = self.game_id.as_deref().unwrap_or("");
+            let body = game_to_json(game, id, self.timeout).to_string();
+
+            request(&self.url, "POST", path, &body, self.timeout)
+        }
+    }
+
+    impl Strategy for HttpBot {
+        fn next_move(&mut self, game: &Game) -> Direction {
+            if self.game_id.is_none() {
+                self.games += 1;
+                self.game_id = Some(format!("rust-snake-{}-{}", game.seed, self.games));
+
+                // The bot can still play when it missed the start
+                if let Err(err) = self.call("/start", game) {
+                    eprintln!("{}", err);
+                }
+            }
+
+            match self.call("/move", game).and_then(|body| parse_move(&body).map_err(|err| format!("Bot at {}: {}", self.url, err))) {
+                Ok(dir) => dir,
+                Err(err) => {
+                    eprintln!("{}, moving {} instead", err, direction_name(game.snake.direction()));
+                    game.snake.direction()
+                }
+            }
+        }
+
+        // A game that was started over never ended, so the bot hears about that first
+        fn reset(&mut self, game: &Game) {
+            self.end(game);
+        }
+
+        fn end(&mut self, game: &Game) {
+            if self.game_id.is_some() {
+                if let Err(err) = self.call("/end", game) {
+                    eprintln!("{}", err);
+                }
+
+                self.game_id = None;
+            }
+        }
+    }
+
+    // A game the server is playing, which keeps its strategy from one move to the next
+    struct Served {
+        // None when the mode doesn't work on this board, the snake then heads for the most space
+        strategy: Option<Box<dyn Strategy>>,
+        food: Vec<Pos>,
+        // Goes up whenever the food changes, the strategies search again when the score does
+        changes: i32,
+        // When the game last had a request, so it can be forgotten if it is never ended
+        seen: Instant,
+    }
+
+    // Plays one of the algorithms as a Battlesnake bot, answering one request at a time
+    pub struct Server {
+        mode: Mode,
+        options: StrategyOptions,
+        color: Rgb,
+        games: HashMap<String, Served>,
+        rng: StdRng,
+    }
+
+    impl Server {
+        pub fn new(mode: Mode, options: StrategyOptions, color: Rgb, seed: u64) -> Self {
+            Self {
+                mode,
+                options,
+                color,
+                games: HashMap::new(),
+                rng: StdRng::seed_from_u64(seed),
+            }
+        }
+
+        pub fn serve(&mut self, address: &str) -> Result<(), String> {
+            let listener = TcpListener::bind(address).map_err(|err| format!("Couldn't listen on {}: {}", address, err))?;
+            println!("Serving {} as a Battlesnake bot on http://{}", self.mode.name(), address);
+
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        eprintln!("Couldn't accept a connection: {}", err);
+                        continue;
+                    }
+                };
+
+                let (status, body) = match read_request(&mut stream, self.options.timeout) {
+                    Ok(request) => match self.handle(&request) {
+                        Ok(Some(json)) => ("200 OK", json),
+                        Ok(None) => ("404 Not Found", Json::object(vec![("error", Json::String(format!("Nothing at {} {}", request.method, request.path)))])),
+                        Err(err) => {
+                            eprintln!("{} {}: {}", request.method, request.path, err);
+                            ("400 Bad Request", Json::object(vec![("error", Json::String(err))]))
+                        }
+                    },
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        continue;
+                    }
+                };
+
+                if let Err(err) = respond(&mut stream, status, &body.to_string()) {
+                    eprintln!("{}", err);
+                }
+            }
+
+            Ok(())
+        }
+
+        // The answer to a request, or None when there is nothing at that path
+        fn handle(&mut self, request: &Request) -> Result<Option<Json>, String> {
+            self.forget_stale(Instant::now());
+
+            let json = || Json::parse(&request.body).map_err(|err| format!("the request isn't valid JSON: {}", err));
+            let id = |json: &Json| json.get("game").and_then(|game| game.get("id")).and_then(|id| id.as_str()).unwrap_or("").to_string();
+
+            match (request.method.as_str(), request.path.trim_end_matches('/')) {
+                ("GET", "") => Ok(Some(self.info())),
+                ("POST", "/start") => {
+                    let json = json()?;
+                    self.start(&id(&json), &json)?;
+
+                    Ok(Some(Json::object(Vec::new())))
+                }
+                ("POST", "/move") => {
+                    let json = json()?;
+                    let dir = self.next_move(&id(&json), &json)?;
+
+                    Ok(Some(Json::object(vec![("move", Json::String(direction_name(dir).to_string()))])))
+                }
+                ("POST", "/end") => {
+                    self.games.remove(&id(&json()?));
+
+                    Ok(Some(Json::object(Vec::new())))
+                }
+                _ => Ok(None),
+            }
+        }
+
+        // Drop the games that haven't had a request for a while, their /end must have gone missing
+        fn forget_stale(&mut self, now: Instant) {
+            let forget_after = Duration::from_secs(SERVE_FORGET_AFTER);
+            self.games.retain(|_, served| now.duration_since(served.seen) < forget_after);
+        }
+
+        // What the Battlesnake servers show for the snake
+        fn info(&self) -> Json {
+            let (r, g, b) = self.color;
+
+            Json::object(vec![
+                ("apiversion", Json::String("1".to_string())),
+                ("author", Json::String("rust_snake".to_string())),
+                ("color", Json::String(format!("#{:02X}{:02X}{:02X}", r, g, b))),
+                ("head", Json::String("default".to_string())),
+                ("tail", Json::String("default".to_string())),
+                ("version", Json::String(format!("{} {}", env!("CARGO_PKG_VERSION"), self.mode.name()))),
+            ])
+        }
+
+        fn start(&mut self, id: &str, json: &Json) -> Result<(), String> {
+            let game = game_from_json(json, self.rng.gen(), false)?;
+
+            let strategy = match create_strategy(self.mode, &self.options, &game) {
+                Ok(strategy) => Some(strategy),
+                Err(err) => {
+                    eprintln!("Game {}: {}, heading for the most space instead", id, err);
+                    None
+                }
+            };
+
+            self.games.insert(id.to_string(), Served { strategy, food: game.food.pieces, changes: 0, seen: Instant::now() });
+
+            Ok(())
+        }
+
+        fn next_move(&mut self, id: &str, json: &Json) -> Result<Direction, String> {
+            // Games the server missed the start of are picked up from wherever they are
+            if !self.games.contains_key(id) {
+                self.start(id, json)?;
+            }
+
+            let mut game = game_from_json(json, self.rng.gen(), true)?;
+            let served = self.games.get_mut(id).expect("The game was just started");
+            served.seen = Instant::now();
+
+            if game.food.pieces != served.food {
+                served.food = game.food.pieces.clone();
+                served.changes += 1;
+            }
+            game.score = served.changes;
+
+            Ok(match served.strategy.as_mut() {
+                Some(strategy) => strategy.next_move(&game),
+                None => game.snake.get_dir_of_free_space(&game.grid),
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn games_survive_a_round_trip_through_json() {
+            let mut grid = Grid::new(11, 9);
+            grid.set_wall(&Pos::new(5, 5), true);
+            grid.set_wall(&Pos::new(0, 8), true);
+
+            let mut game = Game::new(grid, vec![Pos::new(3, 0), Pos::new(2, 0), Pos::new(1, 0)], Vec::new(), 2, 1);
+            game.frame_count = 17;
+
+            // Other snakes are walls in the grid a snake sees, but only go over the wire as snakes
+            let other = Snake::from_body(&game.grid, vec![Pos::new(8, 4), Pos::new(8, 3), Pos::new(7, 3)]);
+            other.body.iter().for_each(|pos| game.grid.set_wall(pos, true));
+            game.others.push(other);
+
+            let text = game_to_json(&game, "game-1", Duration::from_millis(500)).to_string();
+            let json = Json::parse(&text).unwrap();
+            assert_eq!(json.get("you").and_then(|you| you.get("head")), Some(&pos_to_json(&Pos::new(3, 0), &game.grid)));
+            assert_eq!(json.get("you").and_then(|you| you.get("head")).and_then(|head| head.get("y")), Some(&Json::Number(8.0)));
+
+            let back = game_from_json(&json, 1, false).unwrap();
+            assert_eq!((back.grid.width, back.grid.height, back.grid.wrap), (11, 9, false));
+            assert_eq!(back.snake.body, game.snake.body);
+            assert_eq!(back.food.pieces, game.food.pieces);
+            assert_eq!(back.frame_count, 17);
+            assert_eq!(back.others.len(), 1);
+            assert_eq!(back.others[0].body, game.others[0].body);
+            assert_eq!(back.grid.walls(), vec![Pos::new(5, 5), Pos::new(0, 8)]);
+
+            let walled = game_from_json(&json, 1, true).unwrap();
+            assert_eq!(walled.grid.walls().len(), 5);
+        }
+
+        #[test]
+        fn games_that_are_never_ended_are_forgotten() {
+            let options = StrategyOptions {
+                fallback: Fallback::MostSpace,
+                search_every: 1,
+                bots: Vec::new(),
+                timeout: Duration::from_millis(500),
+            };
+            let mut server = Server::new(Mode::AStar, options, (0, 0, 0), 1);
+
+            let game = Game::new(Grid::new(11, 11), vec![Pos::new(3, 0), Pos::new(2, 0), Pos::new(1, 0)], Vec::new(), 1, 1);
+            let json = game_to_json(&game, "game-1", Duration::from_millis(500));
+            server.next_move("game-1", &json).unwrap();
+
+            server.forget_stale(Instant::now());
+            assert_eq!(server.games.len(), 1);
+
+            server.forget_stale(Instant::now() + Duration::from_secs(SERVE_FORGET_AFTER));
+            assert!(server.games.is_empty());
+        }
+
+        #[test]
+        fn one_cell_bodies_are_padded() {
+            let json = Json::parse(r#"{"board": {"width": 11, "height": 11, "snakes": []}, "you": {"id": "a", "body": [{"x": 1, "y": 10}]}}"#).unwrap();
+            let game = game_from_json(&json, 1, false).unwrap();
+
+            assert_eq!(game.snake.body, vec![Pos::new(1, 0), Pos::new(1, 0)]);
+            assert_eq!(game.snake.direction(), Direction::Right);
+        }
+
+        #[test]
+        fn boards_are_capped() {
+            let request = |size: usize| game_from_json(&Json::parse(&format!(r#"{{"board": {{"width": {}, "height": 11}}, "you": {{"body": [{{"x": 0, "y": 0}}]}}}}"#, size)).unwrap(), 1, false).map(|_| ());
+
+            assert!(request(MAX_BOARD).is_ok());
+            assert!(request(MAX_BOARD + 1).is_err());
+            assert!(request(1_000_000_000).is_err());
+        }
+
+        #[test]
+        fn broken_requests() {
+            let request = |text: &str| game_from_json(&Json::parse(text).unwrap(), 1, false).map(|_| ());
+
+            assert!(request(r#"{"you": {"body": [{"x": 0, "y": 0}]}}"#).is_err());
+            assert!(request(r#"{"board": {"width": 1, "height": 11}, "you": {"body": [{"x": 0, "y": 0}]}}"#).is_err());
+            assert!(request(r#"{"board": {"width": 11, "height": 11}, "you": {"body": [{"x": 11, "y": 0}]}}"#).is_err());
+            assert!(request(r#"{"board": {"width": 11, "height": 11}, "you": {"body": []}}"#).is_err());
+        }
+    }
+}
@@ -0,0 +1,144 @@
+pub mod bench {
+    use std::time::{Duration, Instant};
+
+    use crate::game::game::*;
+    use crate::strategy::strategy::*;
+    use crate::STALL_AFTER;
+
+    pub struct GameResult {
+        pub seed: u64,
+        pub score: i32,
+        pub moves: usize,
+        pub win: bool,
+        pub stalled: bool,
+        pub time: Duration,
+    }
+
+    // Play a single game to the end, giving up when the snake stops eating
+    pub fn run_game(game: &mut Game, strategy: &mut dyn Strategy) -> GameResult {
+        let start = Instant::now();
+        let stall_limit = game.grid.width * game.grid.height * STALL_AFTER;
+
+        let mut last_score = game.score;
+        let mut last_eaten = game.frame_count;
+        let mut stalled = false;
+
+        while !game.game_ended() {
+            game.step_with(strategy);
+
+            if game.score != last_score {
+                last_score = game.score;
+                last_eaten = game.frame_count;
+            }
+            else if game.frame_count - last_eaten > stall_limit {
+                stalled = true;
+                break;
+            }
+        }
+
+        GameResult {
+            seed: game.seed,
+            score: game.score,
+            moves: game.frame_count,
+            win: game.game_win(),
+            stalled,
+            time: start.elapsed(),
+        }
+    }
+
+    // With a directory to record to, every replay is saved there as soon as its game is over rather than kept around
+    pub fn run_bench(mode: Mode, options: &StrategyOptions, games: usize, game: &mut Game, record: Option<&str>) -> Result<Vec<GameResult>, String> {
+        let mut strategy = create_strategy(mode, options, game)?;
+        let mut results = Vec::new();
+
+        for i in 0..games {
+            // Every game starts the way a run with its seed does, so the seeds in the report play the same games again
+            if i > 0 {
+                game.reset();
+                strategy = create_strategy(mode, options, game)?;
+            }
+
+            let result = run_game(game, strategy.as_mut());
+
+            if let Some(dir) = record {
+                game.replay.save(&format!("{}/{}.replay", dir, result.seed))?;
+            }
+
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
+    fn percentile(sorted: &[i32], p: usize) -> i32 {
+        sorted[(sorted.len() - 1) * p / 100]
+    }
+
+    pub fn print_report(results: &[GameResult]) {
+        if results.is_empty() {
+            println!("No games played");
+            return;
+        }
+
+        let games = results.len();
+
+        let mut scores: Vec<i32> = results.iter().map(|r| r.score).collect();
+        scores.sort();
+
+        let total_score: i32 = scores.iter().sum();
+        let mean = total_score as f64 / games as f64;
+
+        let wins = results.iter().filter(|r| r.win).count();
+        let stalled = results.iter().filter(|r| r.stalled).count();
+
+        let total_moves: usize = results.iter().map(|r| r.moves).sum();
+        let total_time: Duration = results.iter().map(|r| r.time).sum();
+
+        println!("Games:           {}", games);
+        println!("Score:           min {}, p25 {}, median {}, p75 {}, max {}, mean {:.1}",
+            scores[0], percentile(&scores, 25), percentile(&scores, 50), percentile(&scores, 75), scores[games - 1], mean);
+        println!("Win rate:        {:.1}% ({}/{})", wins as f64 * 100.0 / games as f64, wins, games);
+        println!("Stalled:         {}", stalled);
+
+        if total_score > 0 {
+            println!("Moves per apple: {:.1}", total_moves as f64 / total_score as f64);
+        }
+        else {
+            println!("Moves per apple: -");
+        }
+
+        println!("Time per game:   {:.2?} (total {:.2?})", total_time / games as u32, total_time);
+
+        for result in results.iter().filter(|r| r.stalled) {
+            println!("Stalled game: seed {}, score {}, {} moves", result.seed, result.score, result.moves);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::snake::snake::*;
+
+        #[test]
+        fn seeds_in_the_report_play_the_same_games() {
+            let options = StrategyOptions {
+                fallback: Fallback::MostSpace,
+                search_every: 1,
+                bots: Vec::new(),
+                timeout: Duration::from_millis(500),
+            };
+            let start = vec![Pos::new(2, 0), Pos::new(1, 0), Pos::new(0, 0)];
+
+            let mut game = Game::new(Grid::new(8, 8), start.clone(), Vec::new(), 1, 3);
+            let results = run_bench(Mode::HamiltonianShortcuts, &options, 3, &mut game, None).unwrap();
+
+            for result in results {
+                let mut game = Game::new(Grid::new(8, 8), start.clone(), Vec::new(), 1, result.seed);
+                let again = run_bench(Mode::HamiltonianShortcuts, &options, 1, &mut game, None).unwrap();
+
+                assert_eq!(again[0].score, result.score);
+                assert_eq!(again[0].moves, result.moves);
+            }
+        }
+    }
+}
@@ -0,0 +1,268 @@
+pub mod cli {
+    use std::str::FromStr;
+
+    use crate::settings::settings::*;
+    use crate::snake::snake::*;
+    use crate::strategy::strategy::*;
+    use crate::{SEARCH_EVERY, START_LEN, BENCH_GAMES, MAX_SNAKES, BOT_TIMEOUT, SERVE_ADDRESS};
+
+    pub const USAGE: &str = "\
+Usage: rust_snake [bench | replay <file> | serve] [options]
+
+Options:
+  --mode <mode>          play, astar, astar-safe, ham, ham-short, longest or bot, skips the menu
+  --snakes <modes>       2 to 8 snakes on one board, one mode each as \"astar,longest,play\"
+  --rounds <wins>        rounds a snake has to win to take the match
+  --bot <address>        a Battlesnake bot as http://host:port or a command to run, once for every bot snake
+  --timeout <ms>         how long a bot gets to answer a move
+  --fallback <fallback>  what A* does without a path: random, space or longest
+  --width <cells>        width of the grid
+  --height <cells>       height of the grid
+  --level <file>         play on a level drawn as text, which sets the size of the grid
+  --wrap                 leaving one side of the grid comes back in on the other side
+  --walls <cells>        wall cells as \"x,y x,y ...\", counted from the top left
+  --cell-size <pixels>   size of a cell in the window
+  --speed <frames>       frames between moves while playing yourself
+  --food <count>         pieces of food on the board at once
+  --search-every <moves> moves between searches while A* has no path
+  --start-len <cells>    length of the snake at the start
+  --seed <number>        seed for the food and the algorithms
+  --output <output>      gui, terminal or headless
+  --games <count>        games to play with bench
+  --record <folder>      save a replay of every game played with bench
+  --listen <address>     where serve listens for Battlesnake games, like 127.0.0.1:8000
+  --help                 show this message";
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub enum Command {
+        Play,
+        Bench,
+        Replay,
+        // Play one of the algorithms as a Battlesnake bot
+        Serve,
+        Help
+    }
+
+    // Where a game that is played shows up
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub enum Output {
+        Gui,
+        Terminal,
+        Headless
+    }
+
+    impl FromStr for Output {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "gui" => Ok(Output::Gui),
+                "terminal" => Ok(Output::Terminal),
+                "headless" => Ok(Output::Headless),
+                _ => Err(format!("Unknown output '{}', expected gui, terminal or headless", s)),
+            }
+        }
+    }
+
+    pub struct Args {
+        pub command: Command,
+        pub width: usize,
+        pub height: usize,
+        pub wrap: bool,
+        pub walls: Vec<Pos>,
+        pub level: Option<String>,
+        pub cell_size: usize,
+        pub game_speed: usize,
+        pub food: usize,
+        pub search_every: usize,
+        pub start_len: usize,
+        pub seed: Option<u64>,
+        pub mode: Option<Mode>,
+        // Every snake on a shared board, empty for the usual game with one
+        pub snakes: Vec<Mode>,
+        pub rounds: usize,
+        // The addresses or commands of the bots, in the order the bot snakes come in
+        pub bots: Vec<String>,
+        // Milliseconds a bot gets to answer
+        pub timeout: u64,
+        pub listen: String,
+        pub fallback: Fallback,
+        pub output: Output,
+        pub games: usize,
+        // The replay file to watch, or the folder bench saves a replay of every game to
+        pub replay: Option<String>,
+    }
+
+    impl Args {
+        pub fn parse(settings: &Settings) -> Result<Self, String> {
+            Self::parse_from(std::env::args().skip(1), settings)
+        }
+
+        // Anything that isn't passed comes from the settings
+        pub fn parse_from(args: impl Iterator<Item = String>, settings: &Settings) -> Result<Self, String> {
+            let mut parsed = Self {
+                command: Command::Play,
+                width: settings.width,
+                height: settings.height,
+                wrap: settings.wrap,
+                walls: Vec::new(),
+                level: None,
+                cell_size: settings.cell_size,
+                game_speed: settings.speed,
+                food: settings.food,
+                search_every: SEARCH_EVERY,
+                start_len: START_LEN,
+                seed: None,
+                mode: None,
+                snakes: Vec::new(),
+                rounds: settings.rounds,
+                bots: Vec::new(),
+                timeout: BOT_TIMEOUT,
+                listen: SERVE_ADDRESS.to_string(),
+                fallback: settings.fallback,
+                output: Output::Gui,
+                games: BENCH_GAMES,
+                replay: None,
+            };
+
+            let mut args = args.peekable();
+            match args.peek().map(|arg| arg.as_str()) {
+                Some("bench") => {
+                    parsed.command = Command::Bench;
+                    args.next();
+                }
+                Some("serve") => {
+                    parsed.command = Command::Serve;
+                    args.next();
+                }
+                Some("replay") => {
+                    parsed.command = Command::Replay;
+                    args.next();
+                    parsed.replay = Some(args.next().ok_or("replay needs the path of a replay file")?);
+                }
+                _ => {}
+            }
+
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--width" => parsed.width = value(&arg, args.next())?,
+                    "--height" => parsed.height = value(&arg, args.next())?,
+                    "--level" => parsed.level = Some(value(&arg, args.next())?),
+                    "--wrap" => parsed.wrap = true,
+                    "--walls" => {
+                        let walls = args.next().ok_or(format!("Missing a value for '{}'", arg))?;
+                        parsed.walls = walls.split_whitespace().map(|pos| pos.parse()).collect::<Result<_, String>>()
+                            .map_err(|err| format!("Invalid value '{}' for '{}': {}", walls, arg, err))?;
+                    }
+                    "--cell-size" => parsed.cell_size = value(&arg, args.next())?,
+                    "--speed" => parsed.game_speed = value(&arg, args.next())?,
+                    "--food" => parsed.food = value(&arg, args.next())?,
+                    "--search-every" => parsed.search_every = value(&arg, args.next())?,
+                    "--start-len" => parsed.start_len = value(&arg, args.next())?,
+                    "--seed" => parsed.seed = Some(value(&arg, args.next())?),
+                    "--mode" => parsed.mode = Some(value(&arg, args.next())?),
+                    "--snakes" => {
+                        let snakes = args.next().ok_or(format!("Missing a value for '{}'", arg))?;
+                        parsed.snakes = snakes.split(',').map(|mode| mode.trim().parse()).collect::<Result<_, String>>()
+                            .map_err(|err| format!("Invalid value '{}' for '{}': {}", snakes, arg, err))?;
+                    }
+                    "--rounds" => parsed.rounds = value(&arg, args.next())?,
+                    "--bot" => parsed.bots.push(value(&arg, args.next())?),
+                    "--timeout" => parsed.timeout = value(&arg, args.next())?,
+                    "--fallback" => parsed.fallback = value(&arg, args.next())?,
+                    "--output" if parsed.command == Command::Play => parsed.output = value(&arg, args.next())?,
+                    "--games" if parsed.command == Command::Bench => parsed.games = value(&arg, args.next())?,
+                    "--record" if parsed.command == Command::Bench => parsed.replay = Some(value(&arg, args.next())?),
+                    "--listen" if parsed.command == Command::Serve => parsed.listen = value(&arg, args.next())?,
+                    "--help" | "-h" => parsed.command = Command::Help,
+                    _ => return Err(format!("Unknown argument '{}'\n\n{}", arg, USAGE)),
+                }
+            }
+
+            if parsed.command == Command::Help {
+                return Ok(parsed);
+            }
+
+            if parsed.width < 2 || parsed.height < 2 {
+                return Err(format!("A {}x{} grid is too small to play on", parsed.width, parsed.height));
+            }
+
+            if parsed.level.is_some() && !parsed.walls.is_empty() {
+                return Err("--walls can't be used with --level, draw the walls in the level instead".to_string());
+            }
+
+            // Without a level the snake starts along the top row, a level checks the room behind its own start
+            if parsed.start_len < 2 {
+                return Err("The snake has to start at least 2 cells long".to_string());
+            }
+            else if parsed.level.is_none() && parsed.start_len > parsed.width {
+                return Err(format!("The snake has to start between 2 and {} cells long, the width of the grid", parsed.width));
+            }
+
+            if let Some(wall) = parsed.walls.iter().find(|pos| !Grid::new(parsed.width, parsed.height).in_bounds(pos.x, pos.y)) {
+                return Err(format!("The wall at {},{} is outside of the {}x{} grid", wall.x, wall.y, parsed.width, parsed.height));
+            }
+
+            if let Some(wall) = parsed.walls.iter().find(|pos| pos.y == 0 && pos.x < parsed.start_len) {
+                return Err(format!("The wall at {},{} is in the way of the snake, which starts along the top row", wall.x, wall.y));
+            }
+
+            if parsed.cell_size == 0 || parsed.game_speed == 0 || parsed.search_every == 0 || parsed.food == 0 || parsed.rounds == 0 || parsed.timeout == 0 {
+                return Err("--cell-size, --speed, --search-every, --food, --rounds and --timeout have to be at least 1".to_string());
+            }
+
+            if !parsed.snakes.is_empty() {
+                if parsed.snakes.len() < 2 || parsed.snakes.len() > MAX_SNAKES {
+                    return Err(format!("--snakes takes between 2 and {} modes, got {}", MAX_SNAKES, parsed.snakes.len()));
+                }
+                else if parsed.command != Command::Play || parsed.mode.is_some() || parsed.level.is_some() {
+                    return Err("--snakes can't be used with bench, replay, --mode or --level".to_string());
+                }
+                else if parsed.output == Output::Terminal {
+                    return Err("Several snakes can only be shown in the window".to_string());
+                }
+                else if parsed.output == Output::Headless && parsed.snakes.contains(&Mode::Play) {
+                    return Err("Playing without a screen needs an algorithm for every snake".to_string());
+                }
+            }
+
+            let needs_algorithm = matches!(parsed.command, Command::Bench | Command::Serve) || (parsed.output == Output::Headless && parsed.snakes.is_empty());
+
+            // The window has a menu to pick from, everything else uses the default mode
+            if needs_algorithm || parsed.output != Output::Gui {
+                parsed.mode = parsed.mode.or(settings.mode);
+            }
+
+            if needs_algorithm && matches!(parsed.mode, None | Some(Mode::Play)) {
+                return Err("Playing without a screen needs an algorithm, use --mode astar or --mode ham".to_string());
+            }
+            else if parsed.command == Command::Replay && parsed.mode.is_some() {
+                return Err("--mode can't be used with replay".to_string());
+            }
+            else if parsed.command == Command::Serve && matches!(parsed.mode, Some(Mode::Bot(_))) {
+                return Err("serve plays one of the algorithms, it can't pass the moves on to another bot".to_string());
+            }
+
+            // Every bot snake takes the next address
+            let mut bots = 0;
+            for mode in parsed.snakes.iter_mut().chain(parsed.mode.as_mut()) {
+                if let Mode::Bot(i) = mode {
+                    *i = bots;
+                    bots += 1;
+                }
+            }
+
+            if bots > parsed.bots.len() {
+                return Err(format!("Every bot snake needs its own --bot address, there are {} bot snakes and {} addresses", bots, parsed.bots.len()));
+            }
+
+            Ok(parsed)
+        }
+    }
+
+    fn value<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+        let value = value.ok_or(format!("Missing a value for '{}'", flag))?;
+
+        value.parse().map_err(|_| format!("Invalid value '{}' for '{}'", value, flag))
+    }
+}
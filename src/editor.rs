@@ -0,0 +1,159 @@
+pub mod editor {
+    use raylib::prelude::*;
+
+    use crate::gui::gui::Button;
+    use crate::level::level::*;
+    use crate::render::render::*;
+    use crate::settings::settings::*;
+    use crate::snake::snake::*;
+    use crate::{BUTTON_HEIGHT, BUTTON_FONT_SIZE};
+
+    const HELP: &str = "Left: wall   Right: erase   S: start   F: food   Arrows: direction";
+
+    // Paint a level with the mouse. The level is changed in place, so it becomes the board the next game starts on.
+    // Returns whether the level should be test played.
+    pub fn level_editor(rl: &mut RaylibHandle, thread: &RaylibThread, level: &mut Level, start_len: usize, path: &str, colors: &Colors) -> bool {
+        use raylib::consts::KeyboardKey::*;
+        use raylib::consts::MouseButton::*;
+
+        // The window follows the size of the level, which changes when one is loaded
+        let cell = rl.get_screen_width() / level.grid.width as i32;
+        resize(rl, &level.grid, cell);
+        let mut buttons = toolbar(&level.grid, cell);
+        let mut status = (format!("Editing {}", path), Color::DARKGRAY);
+
+        while !rl.window_should_close() {
+            let toolbar_y = (level.grid.height as i32 * cell) as f32;
+            let pressed = buttons.iter_mut().position(|button| button.update(rl));
+
+            match pressed {
+                Some(0) => {
+                    status = match level.body(start_len).and_then(|_| level.save(path)) {
+                        Ok(()) => (format!("Saved to {}", path), Color::DARKGREEN),
+                        Err(err) => (err, Color::RED),
+                    };
+                }
+                Some(1) => {
+                    status = match Level::load(path) {
+                        Ok(loaded) => {
+                            let wrap = level.grid.wrap;
+
+                            *level = loaded;
+                            level.grid.wrap = wrap;
+                            resize(rl, &level.grid, cell);
+                            buttons = toolbar(&level.grid, cell);
+
+                            (format!("Loaded {}", path), Color::DARKGREEN)
+                        }
+                        Err(err) => (err, Color::RED),
+                    };
+                }
+                Some(2) => {
+                    let grid = Grid::new(level.grid.width, level.grid.height).with_wrap(level.grid.wrap);
+                    *level = Level::new(grid, start_len.min(level.grid.width));
+                    status = ("Cleared the board".to_string(), Color::DARKGRAY);
+                }
+                Some(3) => {
+                    match level.body(start_len) {
+                        Ok(_) => return true,
+                        Err(err) => status = (format!("Can't start the level: {}", err), Color::RED),
+                    }
+                }
+                Some(_) => return false,
+                None => {}
+            }
+
+            let mouse = rl.get_mouse_position();
+            let hovered = match mouse.y < toolbar_y && mouse.x >= 0.0 && mouse.y >= 0.0 {
+                true => Some(Pos::new(mouse.x as usize / cell as usize, mouse.y as usize / cell as usize))
+                    .filter(|pos| level.grid.in_bounds(pos.x, pos.y)),
+                false => None,
+            };
+
+            if let Some(pos) = hovered {
+                if rl.is_mouse_button_down(MOUSE_LEFT_BUTTON) && pos != level.start {
+                    level.grid.set_wall(&pos, true);
+                    level.food.retain(|food| *food != pos);
+                }
+                else if rl.is_mouse_button_down(MOUSE_RIGHT_BUTTON) {
+                    level.grid.set_wall(&pos, false);
+                    level.food.retain(|food| *food != pos);
+                }
+                else if rl.is_key_pressed(KEY_S) {
+                    level.grid.set_wall(&pos, false);
+                    level.food.retain(|food| *food != pos);
+                    level.start = pos;
+                }
+                else if rl.is_key_pressed(KEY_F) && !level.grid.is_wall(&pos) && pos != level.start {
+                    match level.food.contains(&pos) {
+                        true => level.food.retain(|food| *food != pos),
+                        false => level.food.push(pos),
+                    }
+                }
+            }
+
+            for (key, dir) in [(KEY_UP, Direction::Up), (KEY_DOWN, Direction::Down), (KEY_LEFT, Direction::Left), (KEY_RIGHT, Direction::Right)] {
+                if rl.is_key_pressed(key) {
+                    level.direction = dir;
+                }
+            }
+
+            let mut d = rl.begin_drawing(thread);
+            d.clear_background(to_color(colors.background));
+
+            draw_walls(&mut d, &level.grid, cell, colors);
+            for food in level.food.iter() {
+                draw_cell(&mut d, food, cell, to_color(colors.food));
+            }
+
+            // Show the whole snake when it fits, otherwise only where the head goes
+            match level.body(start_len) {
+                Ok(body) => draw_snake(&mut d, &Snake::from_body(&level.grid, body), cell, colors),
+                Err(_) => draw_cell(&mut d, &level.start, cell, to_color(colors.head)),
+            }
+
+            if let Some(pos) = hovered {
+                d.draw_rectangle_lines(pos.x as i32 * cell, pos.y as i32 * cell, cell, cell, Color::BLACK);
+            }
+
+            d.draw_text(HELP, 10, 10, 20, Color::DARKGRAY);
+            d.draw_text(&status.0, 10, toolbar_y as i32 - 25, 20, status.1);
+
+            for button in buttons.iter() {
+                button.draw(&mut d);
+            }
+        }
+
+        false
+    }
+
+    fn toolbar_height(grid: &Grid, cell: i32) -> f32 {
+        (grid.height as i32 * cell) as f32 * BUTTON_HEIGHT / 2.0
+    }
+
+    // The toolbar gets a strip of its own below the board, so every cell can be painted
+    fn resize(rl: &mut RaylibHandle, grid: &Grid, cell: i32) {
+        rl.set_window_size(grid.width as i32 * cell, grid.height as i32 * cell + toolbar_height(grid, cell) as i32);
+    }
+
+    // Save, load, clear, play and back along the bottom of the window, below the board
+    fn toolbar(grid: &Grid, cell: i32) -> Vec<Button> {
+        let screen_width = (grid.width as i32 * cell) as f32;
+        let board_height = (grid.height as i32 * cell) as f32;
+
+        let labels = ["Save", "Load", "Clear", "Play", "Back"];
+        let button_width = screen_width / labels.len() as f32;
+        let button_height = toolbar_height(grid, cell);
+
+        labels.iter().enumerate().map(|(i, label)| Button::new(
+            button_width * i as f32 + 2.0,
+            board_height + 2.0,
+            button_width - 4.0,
+            button_height - 4.0,
+            Color::LIME,
+            Color::DARKGREEN,
+            label.to_string(),
+            Color::BLACK,
+        ).with_font_size(BUTTON_FONT_SIZE / 2.0)).collect()
+    }
+}
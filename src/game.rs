@@ -0,0 +1,169 @@
+pub mod game {
+    use std::cell::RefCell;
+
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use crate::replay::replay::*;
+    use crate::snake::snake::*;
+    use crate::strategy::strategy::*;
+
+    pub struct Game {
+        pub grid: Grid,
+        // The body of the snake at the start of every game, from the head back
+        pub start: Vec<Pos>,
+        pub snake: Snake,
+        // The other snakes when several share the board
+        pub others: Vec<Snake>,
+        pub food: Food,
+        pub score: i32,
+        pub frame_count: usize,
+        pub seed: u64,
+        // Shared so strategies can make random choices while only reading the game
+        pub rng: RefCell<StdRng>,
+        // Every game records itself so it can be saved and watched again
+        pub replay: Replay,
+    }
+
+    impl Game {
+        // Food only spawns on the spawn points while one of them is free, or anywhere when there are none
+        pub fn new(grid: Grid, start: Vec<Pos>, spawns: Vec<Pos>, food_count: usize, seed: u64) -> Self {
+            let mut game = Self {
+                replay: Replay::new(grid.clone(), seed, start.clone(), Vec::new()),
+                snake: Snake::from_body(&grid, start.clone()),
+                grid,
+                start,
+                others: Vec::new(),
+                food: Food { pieces: Vec::new(), count: food_count, spawns },
+                score: 0,
+                frame_count: 0,
+                seed,
+                rng: RefCell::new(StdRng::seed_from_u64(seed)),
+            };
+
+            game.restart(seed);
+            game
+        }
+
+        // Start a new game with a seed drawn from the current one, so a whole session stays reproducible
+        pub fn reset(&mut self) {
+            let seed = self.rng.get_mut().gen();
+            self.restart(seed);
+        }
+
+        // Set up the game from its seed, the same way for a new game and a reset one so the seed is all it takes to play it again
+        fn restart(&mut self, seed: u64) {
+            self.seed = seed;
+            let rng = self.rng.get_mut();
+            *rng = StdRng::seed_from_u64(seed);
+
+            self.snake = Snake::from_body(&self.grid, self.start.clone());
+            self.food.reset(&self.grid, &[&self.snake], rng);
+
+            self.score = 0;
+            self.frame_count = 0;
+            self.replay = Replay::new(self.grid.clone(), self.seed, self.snake.body.clone(), self.food.pieces.clone());
+        }
+
+        pub fn game_over(&self) -> bool {
+            self.snake.game_over
+        }
+
+        pub fn game_win(&self) -> bool {
+            self.snake.game_win
+        }
+
+        pub fn game_ended(&self) -> bool {
+            self.snake.game_ended()
+        }
+
+        pub fn set_next_direction(&mut self, dir: Direction) {
+            self.snake.set_next_direction(dir);
+        }
+
+        // The walls of the board itself. The other snakes show up as walls in the grid a snake sees, but they aren't part of the board.
+        pub fn fixed_walls(&self) -> Vec<Pos> {
+            self.grid.walls().into_iter().filter(|pos| !self.others.iter().any(|other| other.body.contains(pos))).collect()
+        }
+
+        // The piece of food the snake is about to eat, if any
+        pub fn food_ahead(&self) -> Option<usize> {
+            let next = self.snake.head().adjacent(&self.snake.next_direction(), &self.grid)?;
+
+            self.food.pieces.iter().position(|pos| *pos == next)
+        }
+
+        // Advance the game by a single move of the snake
+        pub fn step(&mut self) {
+            if self.game_ended() {
+                return;
+            }
+
+            let score = self.score;
+            let pieces = self.food.pieces.len();
+            let eaten = self.food_ahead();
+            self.replay.moves.push(self.snake.next_direction());
+
+            self.snake.update(&self.grid, &mut self.food, &mut self.score, self.rng.get_mut());
+            self.frame_count += 1;
+
+            // A piece that had no room to respawn is gone, so there is nothing to record
+            if let Some(index) = eaten.filter(|_| self.score != score && self.food.pieces.len() == pieces) {
+                self.replay.food.push(self.food.pieces[index]);
+            }
+        }
+
+        // Let the strategy pick the next direction and step the game with it
+        pub fn step_with(&mut self, strategy: &mut dyn Strategy) {
+            if self.game_ended() {
+                return;
+            }
+
+            let dir = strategy.next_move(self);
+            self.set_next_direction(dir);
+            self.step();
+
+            if self.game_ended() {
+                strategy.end(self);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::time::Duration;
+
+        use super::*;
+
+        fn play(game: &mut Game, moves: usize) {
+            let options = StrategyOptions {
+                fallback: Fallback::MostSpace,
+                search_every: 1,
+                bots: Vec::new(),
+                timeout: Duration::from_millis(500),
+            };
+            let mut strategy = create_strategy(Mode::AStarSafe, &options, game).unwrap();
+
+            for _ in 0..moves {
+                game.step_with(strategy.as_mut());
+            }
+        }
+
+        #[test]
+        fn reset_game_is_the_same_as_a_new_one_with_its_seed() {
+            let start = vec![Pos::new(3, 0), Pos::new(2, 0), Pos::new(1, 0)];
+
+            let mut reset = Game::new(Grid::new(10, 10), start.clone(), Vec::new(), 3, 7);
+            play(&mut reset, 20);
+            reset.reset();
+
+            let mut new = Game::new(Grid::new(10, 10), start, Vec::new(), 3, reset.seed);
+            assert_eq!(new.food.pieces, reset.food.pieces);
+
+            play(&mut reset, 200);
+            play(&mut new, 200);
+            assert_eq!(new.replay.moves, reset.replay.moves);
+            assert_eq!(new.replay.food, reset.replay.food);
+            assert_eq!(new.snake.body, reset.snake.body);
+        }
+    }
+}
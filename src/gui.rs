@@ -1,7 +1,17 @@
 pub mod gui {
     use raylib::{prelude::*, text::measure_text};
 
-    use crate::{SCREEN_WIDTH, BUTTON_WIDTH, SCREEN_HEIGHT, BUTTON_HEIGHT, BUTTON_FONT_SIZE};
+    use crate::render::render::to_color;
+    use crate::settings::settings::*;
+    use crate::strategy::strategy::{Fallback, Mode};
+    use crate::{BUTTON_WIDTH, BUTTON_HEIGHT, BUTTON_FONT_SIZE};
+
+    pub enum MenuChoice {
+        Mode(Mode),
+        TwoPlayers,
+        Settings,
+        Editor,
+    }
 
     pub struct Button {
         rec: Rectangle,
@@ -9,10 +19,13 @@ pub mod gui {
         color_pressed: Color,
         text: String,
         text_color: Color,
+        // Relative to the height of the screen
+        font_size: f32,
         pressed: bool
     }
 
     impl Button {
+        #[allow(clippy::too_many_arguments)]
         pub fn new(x: f32, y: f32, width: f32, height: f32, color: Color, color_pressed: Color, text: String, text_color: Color) -> Self {
             Self {
                 rec: Rectangle { x, y, width, height },
@@ -20,10 +33,16 @@ pub mod gui {
                 color_pressed,
                 text,
                 text_color,
+                font_size: BUTTON_FONT_SIZE,
                 pressed: false,
             }
         }
 
+        pub fn with_font_size(mut self, font_size: f32) -> Self {
+            self.font_size = font_size;
+            self
+        }
+
         fn check_hover(&self, rl: &RaylibHandle) -> bool {
             let mouse_pos = rl.get_mouse_position();
 
@@ -51,11 +70,12 @@ pub mod gui {
         }
 
         fn draw_text_in_center(&self, d: &mut RaylibDrawHandle) {
-            let text_length = measure_text(self.text.as_str(), BUTTON_FONT_SIZE);
+            let font_size = (d.get_screen_height() as f32 * self.font_size) as i32;
+            let text_length = measure_text(self.text.as_str(), font_size);
             let text_x = self.rec.x as i32 + self.rec.width as i32/ 2 - text_length / 2;
-            let text_y = self.rec.y as i32 + self.rec.height as i32/ 2 - BUTTON_FONT_SIZE / 2;
+            let text_y = self.rec.y as i32 + self.rec.height as i32/ 2 - font_size / 2;
         
-            d.draw_text(self.text.as_str(), text_x, text_y, BUTTON_FONT_SIZE, self.text_color);
+            d.draw_text(self.text.as_str(), text_x, text_y, font_size, self.text_color);
         }
 
         pub fn draw(&self, d: &mut RaylibDrawHandle) {
@@ -69,25 +89,42 @@ pub mod gui {
         }
     }
 
-    pub fn mode_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> String {
+    pub fn mode_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> Option<MenuChoice> {
         let chosen = false;
+
+        let screen_width = rl.get_screen_width() as f32;
+        let screen_height = rl.get_screen_height() as f32;
+
+        let button_width = screen_width * BUTTON_WIDTH;
+        let button_height = screen_height * BUTTON_HEIGHT;
     
         let mut self_button = Button::new(
-            SCREEN_WIDTH as f32 / 2.0 - BUTTON_WIDTH / 2.0,
-            SCREEN_HEIGHT as f32 / 3.0 - BUTTON_HEIGHT / 2.0,
-            BUTTON_WIDTH,
-            BUTTON_HEIGHT,
+            screen_width / 4.0 - button_width / 2.0,
+            screen_height / 3.0 - button_height / 2.0,
+            button_width,
+            button_height,
             Color::LIME,
             Color::DARKGREEN,
             "Play".to_string(),
             Color::BLACK,
         );
+
+        let mut longest_button = Button::new(
+            3.0 * screen_width / 4.0 - button_width / 2.0,
+            screen_height / 3.0 - button_height / 2.0,
+            button_width,
+            button_height,
+            Color::LIME,
+            Color::DARKGREEN,
+            "Longest path".to_string(),
+            Color::BLACK,
+        );
     
         let mut astar_button = Button::new(
-            SCREEN_WIDTH as f32 / 4.0 - BUTTON_WIDTH / 2.0,
-            SCREEN_HEIGHT as f32 / 1.5 - BUTTON_HEIGHT / 2.0,
-            BUTTON_WIDTH,
-            BUTTON_HEIGHT,
+            screen_width / 4.0 - button_width / 2.0,
+            screen_height / 1.5 - button_height / 2.0,
+            button_width,
+            button_height,
             Color::LIME,
             Color::DARKGREEN,
             "A*".to_string(),
@@ -95,76 +132,301 @@ pub mod gui {
         );
     
         let mut ham_button = Button::new(
-            3.0 * SCREEN_WIDTH as f32 / 4.0 - BUTTON_WIDTH / 2.0,
-            SCREEN_HEIGHT as f32 / 1.5 - BUTTON_HEIGHT / 2.0,
-            BUTTON_WIDTH,
-            BUTTON_HEIGHT,
+            3.0 * screen_width / 4.0 - button_width / 2.0,
+            screen_height / 1.5 - button_height / 2.0,
+            button_width,
+            button_height,
             Color::LIME,
             Color::DARKGREEN,
             "Hamiltonian".to_string(),
             Color::BLACK,
         );
     
+        let mut settings_button = Button::new(
+            screen_width / 4.0 - button_width / 4.0,
+            screen_height - button_height * 0.75,
+            button_width / 2.0,
+            button_height / 2.0,
+            Color::LIME,
+            Color::DARKGREEN,
+            "Settings".to_string(),
+            Color::BLACK,
+        ).with_font_size(BUTTON_FONT_SIZE / 2.0);
+
+        let mut players_button = Button::new(
+            screen_width / 2.0 - button_width / 4.0,
+            screen_height - button_height * 0.75,
+            button_width / 2.0,
+            button_height / 2.0,
+            Color::LIME,
+            Color::DARKGREEN,
+            "2 Players".to_string(),
+            Color::BLACK,
+        ).with_font_size(BUTTON_FONT_SIZE / 2.0);
+
+        let mut editor_button = Button::new(
+            3.0 * screen_width / 4.0 - button_width / 4.0,
+            screen_height - button_height * 0.75,
+            button_width / 2.0,
+            button_height / 2.0,
+            Color::LIME,
+            Color::DARKGREEN,
+            "Editor".to_string(),
+            Color::BLACK,
+        ).with_font_size(BUTTON_FONT_SIZE / 2.0);
+
         while !rl.window_should_close() && !chosen {
-            if self_button.update(&rl) {
-                return "self".to_string();
+            if self_button.update(rl) {
+                return Some(MenuChoice::Mode(Mode::Play));
+            }
+            else if astar_button.update(rl) {
+                return Some(MenuChoice::Mode(Mode::AStar));
+            }
+            else if ham_button.update(rl) {
+                return Some(MenuChoice::Mode(Mode::Hamiltonian));
             }
-            else if astar_button.update(&rl) {
-                return "a*".to_string();
+            else if longest_button.update(rl) {
+                return Some(MenuChoice::Mode(Mode::LongestPath));
             }
-            else if ham_button.update(&rl) {
-                return "ham".to_string();
+            else if players_button.update(rl) {
+                return Some(MenuChoice::TwoPlayers);
+            }
+            else if settings_button.update(rl) {
+                return Some(MenuChoice::Settings);
+            }
+            else if editor_button.update(rl) {
+                return Some(MenuChoice::Editor);
             }
     
-            let mut d = rl.begin_drawing(&thread);
+            let mut d = rl.begin_drawing(thread);
             d.clear_background(Color::LIGHTGRAY);
     
             self_button.draw(&mut d);
             astar_button.draw(&mut d);
             ham_button.draw(&mut d);
+            longest_button.draw(&mut d);
+            players_button.draw(&mut d);
+            settings_button.draw(&mut d);
+            editor_button.draw(&mut d);
         }
     
-        String::new()
+        None
+    }
+
+    pub fn astar_fallback_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> Option<Fallback> {
+        match choice_menu(rl, thread, &["Random dir", "Most space", "Longest path"])? {
+            0 => Some(Fallback::RandomDir),
+            1 => Some(Fallback::MostSpace),
+            _ => Some(Fallback::LongestPath),
+        }
+    }
+
+    pub fn astar_safe_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> Option<bool> {
+        choice_menu(rl, thread, &["Safe path", "Shortest path"]).map(|choice| choice == 0)
+    }
+
+    pub fn ham_shortcut_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> Option<bool> {
+        choice_menu(rl, thread, &["Shortcuts", "Full cycle"]).map(|choice| choice == 0)
     }
 
-    pub fn astar_random_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> Option<bool> {
-        let mut random_button = Button::new(
-            SCREEN_WIDTH as f32 / 4.0 - BUTTON_WIDTH / 2.0,
-            SCREEN_HEIGHT as f32 / 2.0 - BUTTON_HEIGHT / 2.0,
-            BUTTON_WIDTH,
-            BUTTON_HEIGHT,
+    // The standings at the end of a match, one line per snake in its colour.
+    // Returns whether to play another match.
+    pub fn results_menu(rl: &mut RaylibHandle, thread: &RaylibThread, title: &str, lines: &[(String, Color)]) -> Option<bool> {
+        let screen_width = rl.get_screen_width() as f32;
+        let screen_height = rl.get_screen_height() as f32;
+
+        let button_width = screen_width * BUTTON_WIDTH;
+        let button_height = screen_height * BUTTON_HEIGHT;
+        let font_size = (screen_height * BUTTON_FONT_SIZE) as i32;
+
+        let mut rematch_button = Button::new(
+            screen_width / 4.0 - button_width / 2.0,
+            screen_height - button_height * 1.25,
+            button_width,
+            button_height,
             Color::LIME,
             Color::DARKGREEN,
-            "Random dir".to_string(),
+            "Rematch".to_string(),
             Color::BLACK,
         );
-    
-        let mut choose_button = Button::new(
-            3.0 * SCREEN_WIDTH as f32 / 4.0 - BUTTON_WIDTH / 2.0,
-            SCREEN_HEIGHT as f32 / 2.0 - BUTTON_HEIGHT / 2.0,
-            BUTTON_WIDTH,
-            BUTTON_HEIGHT,
+
+        let mut back_button = Button::new(
+            3.0 * screen_width / 4.0 - button_width / 2.0,
+            screen_height - button_height * 1.25,
+            button_width,
+            button_height,
             Color::LIME,
             Color::DARKGREEN,
-            "Most space".to_string(),
+            "Back".to_string(),
             Color::BLACK,
         );
-    
+
         while !rl.window_should_close() {
-            if random_button.update(&rl) {
-                return Some(true)
+            if rematch_button.update(rl) {
+                return Some(true);
             }
-            else if choose_button.update(&rl) {
-                return Some(false)
+            else if back_button.update(rl) {
+                return Some(false);
+            }
+
+            let mut d = rl.begin_drawing(thread);
+            d.clear_background(Color::LIGHTGRAY);
+
+            let title_length = measure_text(title, font_size);
+            d.draw_text(title, screen_width as i32 / 2 - title_length / 2, font_size, font_size, Color::BLACK);
+
+            for (i, (line, color)) in lines.iter().enumerate() {
+                let y = font_size * 3 + i as i32 * font_size * 3 / 4;
+                d.draw_text(line, (screen_width * 0.1) as i32, y, font_size / 2, *color);
             }
+
+            rematch_button.draw(&mut d);
+            back_button.draw(&mut d);
+        }
+
+        None
+    }
+
+    // Stacks the choices from top to bottom and returns the index of the one that was picked
+    fn choice_menu(rl: &mut RaylibHandle, thread: &RaylibThread, choices: &[&str]) -> Option<usize> {
+        let screen_width = rl.get_screen_width() as f32;
+        let screen_height = rl.get_screen_height() as f32;
+
+        let button_width = screen_width * BUTTON_WIDTH;
+        let button_height = screen_height * BUTTON_HEIGHT;
+
+        let mut buttons: Vec<Button> = choices.iter().enumerate().map(|(i, choice)| Button::new(
+            screen_width / 2.0 - button_width / 2.0,
+            screen_height * (i + 1) as f32 / (choices.len() + 1) as f32 - button_height / 2.0,
+            button_width,
+            button_height,
+            Color::LIME,
+            Color::DARKGREEN,
+            choice.to_string(),
+            Color::BLACK,
+        )).collect();
     
-            let mut d = rl.begin_drawing(&thread);
+        while !rl.window_should_close() {
+            for (i, button) in buttons.iter_mut().enumerate() {
+                if button.update(rl) {
+                    return Some(i)
+                }
+            }
+    
+            let mut d = rl.begin_drawing(thread);
             d.clear_background(Color::LIGHTGRAY);
     
-            random_button.draw(&mut d);
-            choose_button.draw(&mut d);
+            for button in buttons.iter() {
+                button.draw(&mut d);
+            }
+        }
+
+        None
+    }
+
+    // Every setting gets a row with buttons to step through its values, keys are changed by clicking and pressing the new key.
+    // Returns whether the settings were saved.
+    pub fn settings_menu(rl: &mut RaylibHandle, thread: &RaylibThread, settings: &mut Settings) -> bool {
+        let screen_width = rl.get_screen_width() as f32;
+        let screen_height = rl.get_screen_height() as f32;
+
+        let rows = Field::ALL.len() + 2;
+        let row_height = screen_height / rows as f32;
+        let font_size = BUTTON_FONT_SIZE / 2.0;
+
+        let small_button = |x: f32, y: f32, text: &str| Button::new(
+            x,
+            y + row_height * 0.1,
+            screen_width * 0.08,
+            row_height * 0.8,
+            Color::LIME,
+            Color::DARKGREEN,
+            text.to_string(),
+            Color::BLACK,
+        ).with_font_size(font_size);
+
+        let mut rows: Vec<(Field, Button, Button)> = Field::ALL.iter().enumerate().map(|(i, field)| {
+            let y = row_height * i as f32;
+            (*field, small_button(screen_width * 0.5, y, "<"), small_button(screen_width * 0.88, y, ">"))
+        }).collect();
+
+        let bottom = row_height * Field::ALL.len() as f32 + row_height * 0.5;
+        let mut save_button = Button::new(screen_width * 0.2, bottom, screen_width * 0.25, row_height, Color::LIME, Color::DARKGREEN, "Save".to_string(), Color::BLACK)
+            .with_font_size(font_size);
+        let mut back_button = Button::new(screen_width * 0.55, bottom, screen_width * 0.25, row_height, Color::LIME, Color::DARKGREEN, "Back".to_string(), Color::BLACK)
+            .with_font_size(font_size);
+
+        let mut edited = settings.clone();
+        let mut rebinding: Option<Field> = None;
+        let mut error = String::new();
+
+        while !rl.window_should_close() {
+            if let Some(field) = rebinding {
+                if let Some(key) = rl.get_key_pressed() {
+                    if let Some(name) = key_name(key as i32) {
+                        let _ = edited.set(field, &name);
+                        rebinding = None;
+                    }
+                }
+            }
+
+            for (field, left, right) in rows.iter_mut() {
+                let left_pressed = left.update(rl);
+                let right_pressed = right.update(rl);
+
+                if field.is_key() && (left_pressed || right_pressed) {
+                    rebinding = Some(*field);
+                }
+                else if left_pressed || right_pressed {
+                    edited.change(*field, right_pressed);
+                }
+            }
+
+            if save_button.update(rl) {
+                match edited.save() {
+                    Ok(()) => {
+                        *settings = edited;
+                        return true;
+                    }
+                    Err(err) => error = err,
+                }
+            }
+            else if back_button.update(rl) {
+                return false;
+            }
+
+            let text_size = (screen_height * font_size) as i32;
+
+            let mut d = rl.begin_drawing(thread);
+            d.clear_background(Color::LIGHTGRAY);
+
+            for (i, (field, left, right)) in rows.iter().enumerate() {
+                let y = (row_height * i as f32 + row_height / 2.0) as i32 - text_size / 2;
+                d.draw_text(field.label(), (screen_width * 0.05) as i32, y, text_size, Color::BLACK);
+
+                let value = match rebinding == Some(*field) {
+                    true => "Press a key".to_string(),
+                    false => edited.value(*field),
+                };
+
+                let value_x = (screen_width * 0.73) as i32 - measure_text(&value, text_size) / 2;
+                if let Some(color) = edited.color(*field) {
+                    d.draw_rectangle(value_x - text_size - 5, y, text_size, text_size, to_color(color));
+                }
+                d.draw_text(&value, value_x, y, text_size, Color::BLACK);
+
+                left.draw(&mut d);
+                right.draw(&mut d);
+            }
+
+            save_button.draw(&mut d);
+            back_button.draw(&mut d);
+
+            if !error.is_empty() {
+                d.draw_text(&error, 10, (bottom + row_height * 1.1) as i32, text_size, Color::RED);
+            }
         }
 
-        return None
+        false
     }
-}
\ No newline at end of file
+}
@@ -1,71 +1,333 @@
-
 pub mod ham_cycle {
-    use crate::{snake::snake::*, GRID_WIDTH, GRID_HEIGHT};
+    use rand::{Rng, seq::SliceRandom};
+
+    use crate::{game::game::*, snake::snake::*, strategy::strategy::*, CYCLE_ATTEMPTS, SHORTCUTS_UNTIL};
+
+    // A spanning tree on the grid of 2x2 blocks
+    struct Tree {
+        width: usize,
+        right: Vec<bool>,
+        down: Vec<bool>,
+    }
+
+    impl Tree {
+        fn has_edge(&self, x: usize, y: usize, dir: Direction) -> bool {
+            match dir {
+                Direction::Right => self.right[y * self.width + x],
+                Direction::Down => self.down[y * self.width + x],
+                Direction::Left => x > 0 && self.right[y * self.width + x - 1],
+                Direction::Up => y > 0 && self.down[(y - 1) * self.width + x],
+            }
+        }
+    }
+
+    fn find(parents: &mut Vec<usize>, i: usize) -> usize {
+        if parents[i] != i {
+            let root = find(parents, parents[i]);
+            parents[i] = root;
+        }
+
+        parents[i]
+    }
 
     pub struct HamiltonianCycle {
-        path: Vec<Direction>,
-        path_index: usize
+        cycle: Vec<Pos>,
+        // The index of every cell in the cycle, stored at y * width + x
+        order: Vec<usize>,
+        width: usize,
+        shortcuts: bool
     }
-    
+
     impl HamiltonianCycle {
-        pub fn new() -> Self {
+        pub fn new(shortcuts: bool) -> Self {
             Self {
-                path: Vec::new(),
-                path_index: 0
+                cycle: Vec::new(),
+                order: Vec::new(),
+                width: 0,
+                shortcuts
+            }
+        }
+
+        pub fn index_of(&self, pos: &Pos) -> usize {
+            self.order[pos.y * self.width + pos.x]
+        }
+
+        fn next_pos(&self, pos: &Pos) -> Pos {
+            self.cycle[(self.index_of(pos) + 1) % self.cycle.len()]
+        }
+
+        // How many steps it takes to get from one cell to the other when following the cycle
+        fn distance(&self, from: &Pos, to: &Pos) -> usize {
+            (self.index_of(to) + self.cycle.len() - self.index_of(from)) % self.cycle.len()
+        }
+
+        // The body always stays in the order of the cycle, so the cells between the head and the tail are free.
+        // Jumping ahead is safe as long as it doesn't pass the closest food and leaves enough room in front of the tail
+        // for the snake to double in length before the tail catches up.
+        fn shortcut(&self, game: &Game) -> Option<Pos> {
+            let snake = &game.snake;
+            let len = snake.body.len();
+
+            if len as f32 >= self.cycle.len() as f32 * SHORTCUTS_UNTIL {
+                return None;
+            }
+
+            let head = snake.head();
+            let tail = snake.body[len - 1];
+
+            let to_tail = self.distance(&head, &tail);
+            let to_food = game.food.pieces.iter().map(|food| self.distance(&head, food)).min().unwrap_or(0);
+
+            let mut best = None;
+            let mut best_distance = 1;
+
+            for pos in head.get_neighbors(&game.grid) {
+                let distance = self.distance(&head, &pos);
+
+                if distance > best_distance && distance <= to_food && distance + len < to_tail {
+                    best = Some(pos);
+                    best_distance = distance;
+                }
+            }
+
+            best
+        }
+
+        pub fn generate(&mut self, grid: &Grid, snake: &Snake, rng: &mut impl Rng) -> Result<(), String> {
+            if !grid.walls().is_empty() {
+                Err("Hamiltonian cycles need a board without walls".to_string())
+            }
+            else if grid.width.is_multiple_of(2) && grid.height.is_multiple_of(2) {
+                for _ in 0..CYCLE_ATTEMPTS {
+                    let tree = Self::spanning_tree(grid, snake, rng);
+                    let cycle = Self::trace(grid, &tree);
+
+                    if self.set_cycle(grid, snake, cycle)? {
+                        return Ok(());
+                    }
+                }
+
+                Err("Couldn't find a Hamiltonian cycle that follows the body of the snake".to_string())
+            }
+            // Boards with an odd width still have the simple back and forth cycle
+            else if grid.height.is_multiple_of(2) {
+                match self.set_cycle(grid, snake, Self::zigzag(grid))? {
+                    true => Ok(()),
+                    false => Err("The snake has to start along the top row to follow the Hamiltonian cycle".to_string()),
+                }
+            }
+            else {
+                Err(format!("Can't generate a Hamiltonian cycle on a {}x{} grid, the height has to be even", grid.width, grid.height))
             }
         }
 
-        pub fn update(&mut self, snake: &mut Snake, food: &mut Food, score: &mut i32) {
-            snake.set_next_direction(self.get_next_move());
+        // Use the cycle if the snake can follow it in either direction, the current cycle is kept otherwise
+        fn set_cycle(&mut self, grid: &Grid, snake: &Snake, mut cycle: Vec<Pos>) -> Result<bool, String> {
+            Self::validate(grid, &cycle)?;
 
-            snake.update(food, score);
+            for _ in 0..2 {
+                let mut order = vec![0; grid.width * grid.height];
+                for (i, pos) in cycle.iter().enumerate() {
+                    order[pos.y * grid.width + pos.x] = i;
+                }
+
+                let candidate = Self {
+                    cycle,
+                    order,
+                    width: grid.width,
+                    shortcuts: self.shortcuts,
+                };
+
+                if candidate.follows_body(snake) {
+                    *self = candidate;
+                    return Ok(true);
+                }
+
+                cycle = candidate.cycle.into_iter().rev().collect();
+            }
 
-            self.path_index += 1;
-            self.path_index %= self.path.len() - 1;
+            Ok(false)
         }
 
-        fn get_next_move(&self) -> Direction {
-            self.path[self.path_index]
+        // A snake that just grew, or a Battlesnake at the start of a game, has cells of its body on top of each other
+        fn follows_body(&self, snake: &Snake) -> bool {
+            snake.body.windows(2).all(|pair| pair[0] == pair[1] || self.next_pos(&pair[1]) == pair[0])
         }
 
-        pub fn reset(&mut self, snake: &Snake) {
-            self.path_index = snake.len() - 1;
+        pub fn validate(grid: &Grid, cycle: &[Pos]) -> Result<(), String> {
+            if cycle.len() != grid.width * grid.height {
+                return Err(format!("The cycle has {} cells but the grid has {}", cycle.len(), grid.width * grid.height));
+            }
+
+            let mut visited = vec![false; grid.width * grid.height];
+            for (i, pos) in cycle.iter().enumerate() {
+                if !grid.in_bounds(pos.x, pos.y) || visited[pos.y * grid.width + pos.x] {
+                    return Err(format!("The cycle visits {:?} more than once", pos));
+                }
+                visited[pos.y * grid.width + pos.x] = true;
+
+                let next = cycle[(i + 1) % cycle.len()];
+                if pos.get_dir_to(&next, grid).is_none() {
+                    return Err(format!("The cycle jumps from {:?} to {:?}", pos, next));
+                }
+            }
+
+            Ok(())
         }
 
-        pub fn generate(&mut self, snake: &Snake) {
-            // Go all the way to the right
-            for _ in 1..GRID_WIDTH {
-                self.path.push(Direction::Right);
+        // Kruskal's algorithm with random edge weights, keeping the edges the body of the snake already crosses
+        fn spanning_tree(grid: &Grid, snake: &Snake, rng: &mut impl Rng) -> Tree {
+            let width = grid.width / 2;
+            let height = grid.height / 2;
+
+            let mut tree = Tree {
+                width,
+                right: vec![false; width * height],
+                down: vec![false; width * height],
+            };
+            let mut parents: Vec<usize> = (0..width * height).collect();
+
+            let mut forced = Vec::new();
+            for pair in snake.body.windows(2) {
+                let from = Pos::new(pair[1].x / 2, pair[1].y / 2);
+                let to = Pos::new(pair[0].x / 2, pair[0].y / 2);
+
+                if from != to {
+                    let horizontal = from.y == to.y;
+                    let pos = match horizontal {
+                        true => Pos::new(from.x.min(to.x), from.y),
+                        false => Pos::new(from.x, from.y.min(to.y)),
+                    };
+
+                    forced.push((pos, horizontal));
+                }
             }
-            // Go down one
-            self.path.push(Direction::Down);
 
-            // Loop back and forth while leaving one gap
-            let mut reverse = true;
-            for _y in 1..GRID_HEIGHT {
-                for _x in 2..GRID_WIDTH {
-                    if reverse {
-                        self.path.push(Direction::Left);
+            let mut edges = Vec::new();
+            for y in 0..height {
+                for x in 0..width {
+                    if x + 1 < width {
+                        edges.push((Pos::new(x, y), true));
                     }
-                    else {
-                        self.path.push(Direction::Right);
+                    if y + 1 < height {
+                        edges.push((Pos::new(x, y), false));
                     }
                 }
+            }
+            edges.shuffle(rng);
+
+            for (pos, horizontal) in forced.into_iter().chain(edges) {
+                let other = match horizontal {
+                    true => Pos::new(pos.x + 1, pos.y),
+                    false => Pos::new(pos.x, pos.y + 1),
+                };
+
+                let a = find(&mut parents, pos.y * width + pos.x);
+                let b = find(&mut parents, other.y * width + other.x);
+
+                if a != b {
+                    parents[a] = b;
+
+                    match horizontal {
+                        true => tree.right[pos.y * width + pos.x] = true,
+                        false => tree.down[pos.y * width + pos.x] = true,
+                    }
+                }
+            }
+
+            tree
+        }
+
+        // Walk clockwise around the spanning tree, which passes every cell once
+        fn trace(grid: &Grid, tree: &Tree) -> Vec<Pos> {
+            let mut cycle = Vec::new();
+            let mut cur = Pos::new(0, 0);
+
+            for _ in 0..grid.width * grid.height {
+                cycle.push(cur);
+
+                let x = cur.x / 2;
+                let y = cur.y / 2;
+
+                let dir = match (cur.x % 2, cur.y % 2) {
+                    (0, 0) => if tree.has_edge(x, y, Direction::Up) { Direction::Up } else { Direction::Right },
+                    (1, 0) => if tree.has_edge(x, y, Direction::Right) { Direction::Right } else { Direction::Down },
+                    (1, 1) => if tree.has_edge(x, y, Direction::Down) { Direction::Down } else { Direction::Left },
+                    _ => if tree.has_edge(x, y, Direction::Left) { Direction::Left } else { Direction::Up },
+                };
+
+                cur = cur.transform(&dir);
+            }
+
+            cycle
+        }
+
+        // Go along the top, loop back and forth while leaving the first column free, then go back up
+        fn zigzag(grid: &Grid) -> Vec<Pos> {
+            let mut cycle = Vec::new();
+
+            for x in 0..grid.width {
+                cycle.push(Pos::new(x, 0));
+            }
+
+            for y in 1..grid.height {
+                for i in 1..grid.width {
+                    let x = match y % 2 {
+                        1 => grid.width - i,
+                        _ => i,
+                    };
+                    cycle.push(Pos::new(x, y));
+                }
+            }
 
-                self.path.push(Direction::Down);
-                reverse = !reverse;
+            for y in (1..grid.height).rev() {
+                cycle.push(Pos::new(0, y));
             }
 
-            // Go to the edge
-            self.path.pop();
-            self.path.push(Direction::Left);
+            cycle
+        }
+    }
+
+    impl Strategy for HamiltonianCycle {
+        fn next_move(&mut self, game: &Game) -> Direction {
+            let head = game.snake.head();
+
+            let next = match self.shortcuts {
+                true => self.shortcut(game).unwrap_or(self.next_pos(&head)),
+                false => self.next_pos(&head),
+            };
+
+            // Another snake on the cycle shows up as a wall, so get out of its way
+            head.get_dir_to(&next, &game.grid).unwrap_or_else(|| game.snake.get_dir_of_free_space(&game.grid))
+        }
 
-            // Go back up
-            for _y in 0..GRID_HEIGHT {
-                self.path.push(Direction::Up);
+        fn reset(&mut self, game: &Game) {
+            // Every game gets a new cycle. Games start with the same body, so the old cycle still fits when that fails.
+            if let Err(err) = self.generate(&game.grid, &game.snake, &mut *game.rng.borrow_mut()) {
+                eprintln!("Couldn't make a new Hamiltonian cycle, keeping the old one: {}", err);
             }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        use super::*;
+
+        #[test]
+        fn a_cycle_that_doesnt_fit_leaves_the_old_one() {
+            let grid = Grid::new(5, 4);
+            let mut rng = StdRng::seed_from_u64(1);
+
+            let along_the_top = Snake::from_body(&grid, vec![Pos::new(2, 0), Pos::new(1, 0), Pos::new(0, 0)]);
+            let mut ham = HamiltonianCycle::new(false);
+            ham.generate(&grid, &along_the_top, &mut rng).unwrap();
 
-            self.path_index = snake.len() - 1;
+            // Neither way around the back and forth cycle goes from (2, 3) to (2, 2)
+            let across = Snake::from_body(&grid, vec![Pos::new(2, 2), Pos::new(2, 3)]);
+            assert!(ham.generate(&grid, &across, &mut rng).is_err());
+            assert!(ham.follows_body(&along_the_top));
         }
     }
-}
\ No newline at end of file
+}
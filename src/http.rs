@@ -0,0 +1,334 @@
+pub mod http {
+    use std::fmt;
+    use std::io::{Read, Write};
+    use std::net::{TcpStream, ToSocketAddrs};
+    use std::str::FromStr;
+    use std::time::{Duration, Instant};
+
+    // The most a request may send, headers and all. Battlesnake requests take a few kilobytes.
+    const MAX_REQUEST: usize = 1 << 20;
+
+    // Where a server is listening, from an address like http://localhost:8000/snake
+    #[derive(Clone, PartialEq, Debug)]
+    pub struct Url {
+        pub host: String,
+        pub port: u16,
+        // Without the trailing slash, so paths can be added to it
+        pub path: String,
+    }
+
+    impl FromStr for Url {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let rest = s.strip_prefix("http://").ok_or(format!("'{}' isn't an http:// address", s))?;
+
+            let (authority, path) = match rest.find('/') {
+                Some(i) => (&rest[..i], rest[i..].trim_end_matches('/')),
+                None => (rest, ""),
+            };
+
+            let (host, port) = match authority.rsplit_once(':') {
+                Some((host, port)) => (host, port.parse().map_err(|_| format!("'{}' isn't a port in '{}'", port, s))?),
+                None => (authority, 80),
+            };
+
+            if host.is_empty() {
+                return Err(format!("'{}' is missing the host", s));
+            }
+
+            Ok(Self {
+                host: host.to_string(),
+                port,
+                path: path.to_string(),
+            })
+        }
+    }
+
+    impl fmt::Display for Url {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "http://{}:{}{}", self.host, self.port, self.path)
+        }
+    }
+
+    // The time left before the deadline, or an error once it has passed
+    fn remaining(deadline: Instant) -> Result<Duration, String> {
+        deadline.checked_duration_since(Instant::now())
+            .filter(|left| !left.is_zero())
+            .ok_or("timed out".to_string())
+    }
+
+    // Send a request over a new connection and return the body of the answer, giving up once the timeout has passed
+    pub fn request(url: &Url, method: &str, path: &str, body: &str, timeout: Duration) -> Result<String, String> {
+        let deadline = Instant::now() + timeout;
+
+        let addr = (url.host.as_str(), url.port).to_socket_addrs().ok().and_then(|mut addrs| addrs.next())
+            .ok_or(format!("Couldn't find {}", url))?;
+        let mut stream = TcpStream::connect_timeout(&addr, timeout).map_err(|err| format!("Couldn't connect to {}: {}", url, err))?;
+
+        let request = format!(
+            "{} {}{} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method, url.path, path, url.host, url.port, body.len(), body,
+        );
+
+        let failed = |err: String| format!("{} {}{} failed: {}", method, url, path, err);
+
+        stream.set_write_timeout(Some(remaining(deadline).map_err(failed)?)).map_err(|err| failed(err.to_string()))?;
+        stream.write_all(request.as_bytes()).map_err(|err| failed(err.to_string()))?;
+
+        let mut response = Vec::new();
+        let mut buf = [0; 4096];
+
+        loop {
+            stream.set_read_timeout(Some(remaining(deadline).map_err(failed)?)).map_err(|err| failed(err.to_string()))?;
+
+            let read = match stream.read(&mut buf) {
+                Ok(read) => read,
+                Err(err) if matches!(err.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => return Err(failed("timed out".to_string())),
+                Err(err) => return Err(failed(err.to_string())),
+            };
+            response.extend_from_slice(&buf[..read]);
+
+            // Servers that keep the connection open anyway are done once the whole body is there
+            if let Some(result) = parse_response(&response, read == 0) {
+                return result.map_err(failed);
+            }
+        }
+    }
+
+    // The body once the whole response has arrived, or None while more is on the way
+    fn parse_response(response: &[u8], closed: bool) -> Option<Result<String, String>> {
+        let text = String::from_utf8_lossy(response);
+
+        let (head, body) = match text.split_once("\r\n\r\n") {
+            Some(parts) => parts,
+            None if closed => return Some(Err("the connection closed before the answer was complete".to_string())),
+            None => return None,
+        };
+
+        let status = head.lines().next().unwrap_or("");
+
+        let body = if let Some(length) = header(head, "content-length").and_then(|length| length.parse::<usize>().ok()) {
+            match body.len() >= length {
+                true => body.get(..length).unwrap_or(body).to_string(),
+                false if closed => return Some(Err("the connection closed before the answer was complete".to_string())),
+                false => return None,
+            }
+        }
+        else if header(head, "transfer-encoding").is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked")) {
+            match dechunk(body) {
+                Some(body) => body,
+                None if closed => return Some(Err("the connection closed before the answer was complete".to_string())),
+                None => return None,
+            }
+        }
+        else if closed {
+            body.to_string()
+        }
+        else {
+            return None;
+        };
+
+        match status.split_whitespace().nth(1) {
+            Some(code) if code.starts_with('2') => Some(Ok(body)),
+            _ => Some(Err(format!("the server answered '{}'", status))),
+        }
+    }
+
+    // The value of a header, the first line with the status or the request is skipped
+    fn header(head: &str, name: &str) -> Option<String> {
+        head.lines().skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim().to_string())
+    }
+
+    // A request as the server sees it
+    pub struct Request {
+        pub method: String,
+        pub path: String,
+        pub body: String,
+    }
+
+    // Read a whole request from a client, which has to send all of it within the timeout
+    pub fn read_request(stream: &mut TcpStream, timeout: Duration) -> Result<Request, String> {
+        let deadline = Instant::now() + timeout;
+        let failed = |err: String| format!("couldn't read the request: {}", err);
+
+        let mut data = Vec::new();
+        let mut buf = [0; 4096];
+
+        loop {
+            stream.set_read_timeout(Some(remaining(deadline).map_err(failed)?)).map_err(|err| failed(err.to_string()))?;
+
+            let read = match stream.read(&mut buf) {
+                Ok(read) => read,
+                Err(err) if matches!(err.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => return Err(failed("timed out".to_string())),
+                Err(err) => return Err(failed(err.to_string())),
+            };
+            data.extend_from_slice(&buf[..read]);
+
+            if data.len() > MAX_REQUEST {
+                return Err(failed(format!("it is over {} bytes", MAX_REQUEST)));
+            }
+
+            let text = String::from_utf8_lossy(&data);
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = header(head, "content-length").and_then(|length| length.parse().ok()).unwrap_or(0);
+
+                if length > MAX_REQUEST.saturating_sub(head.len()) {
+                    return Err(failed(format!("it is over {} bytes", MAX_REQUEST)));
+                }
+
+                if body.len() >= length {
+                    let mut request_line = head.lines().next().unwrap_or("").split_whitespace();
+
+                    return Ok(Request {
+                        method: request_line.next().unwrap_or("").to_string(),
+                        path: request_line.next().unwrap_or("").to_string(),
+                        body: body.get(..length).unwrap_or(body).to_string(),
+                    });
+                }
+            }
+
+            if read == 0 {
+                return Err("the connection closed in the middle of the request".to_string());
+            }
+        }
+    }
+
+    // Answer with a JSON body and close the connection
+    pub fn respond(stream: &mut TcpStream, status: &str, body: &str) -> Result<(), String> {
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, body.len(), body,
+        );
+
+        stream.write_all(response.as_bytes()).map_err(|err| format!("couldn't answer: {}", err))
+    }
+
+    // Put a chunked body back together, None until the last chunk is there
+    fn dechunk(mut body: &str) -> Option<String> {
+        let mut joined = String::new();
+
+        loop {
+            let (size, rest) = body.split_once("\r\n")?;
+            let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+
+            if size == 0 {
+                return Some(joined);
+            }
+
+            joined += rest.get(..size)?;
+            body = rest.get(size..)?.strip_prefix("\r\n")?;
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::net::TcpListener;
+        use std::thread;
+
+        use super::*;
+
+        #[test]
+        fn urls() {
+            let url: Url = "http://localhost:8000/snake/".parse().unwrap();
+            assert_eq!(url, Url { host: "localhost".to_string(), port: 8000, path: "/snake".to_string() });
+            assert_eq!(url.to_string(), "http://localhost:8000/snake");
+
+            assert_eq!("http://example.com".parse::<Url>().map(|url| url.port), Ok(80));
+            assert!("https://example.com".parse::<Url>().is_err());
+            assert!("http://:80".parse::<Url>().is_err());
+            assert!("http://host:port".parse::<Url>().is_err());
+        }
+
+        #[test]
+        fn content_length_bodies() {
+            let response = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+
+            assert_eq!(parse_response(response, false), Some(Ok("hello".to_string())));
+            assert_eq!(parse_response(&response[..response.len() - 2], false), None);
+            assert!(matches!(parse_response(&response[..response.len() - 2], true), Some(Err(_))));
+            assert_eq!(parse_response(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nhi there", false), Some(Ok("hi".to_string())));
+        }
+
+        #[test]
+        fn chunked_bodies() {
+            let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n";
+
+            assert_eq!(parse_response(response, false), Some(Ok("hello, world".to_string())));
+            assert_eq!(parse_response(&response[..40], false), None);
+            assert!(matches!(parse_response(&response[..40], true), Some(Err(_))));
+            assert_eq!(dechunk("a\r\n0123456789\r\n0\r\n\r\n"), Some("0123456789".to_string()));
+            assert_eq!(dechunk("zz\r\n"), None);
+        }
+
+        #[test]
+        fn bodies_without_a_length_end_with_the_connection() {
+            let response = b"HTTP/1.1 200 OK\r\n\r\nuntil closed";
+
+            assert_eq!(parse_response(response, false), None);
+            assert_eq!(parse_response(response, true), Some(Ok("until closed".to_string())));
+        }
+
+        // Send the request from another thread, a piece at a time, and read it on this one
+        fn send(pieces: Vec<Vec<u8>>, pause: Duration, timeout: Duration) -> Result<Request, String> {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let address = listener.local_addr().unwrap();
+
+            let client = thread::spawn(move || {
+                let mut stream = TcpStream::connect(address).unwrap();
+                for piece in pieces {
+                    if stream.write_all(&piece).is_err() {
+                        break;
+                    }
+                    thread::sleep(pause);
+                }
+            });
+
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = read_request(&mut stream, timeout);
+            drop(stream);
+            client.join().unwrap();
+
+            request
+        }
+
+        #[test]
+        fn requests() {
+            let request = send(vec![b"POST /move HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}".to_vec()], Duration::ZERO, Duration::from_secs(5)).unwrap();
+
+            assert_eq!((request.method.as_str(), request.path.as_str(), request.body.as_str()), ("POST", "/move", "{}"));
+        }
+
+        #[test]
+        fn requests_that_trickle_in_time_out() {
+            let pieces = b"POST /move HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}".iter().map(|byte| vec![*byte]).collect();
+            let start = Instant::now();
+
+            assert!(send(pieces, Duration::from_millis(20), Duration::from_millis(200)).is_err());
+            assert!(start.elapsed() < Duration::from_secs(2));
+        }
+
+        #[test]
+        fn requests_that_are_too_big_are_refused() {
+            let head = b"POST /move HTTP/1.1\r\nContent-Length: 4000000000\r\n\r\n".to_vec();
+            assert!(send(vec![head], Duration::ZERO, Duration::from_secs(5)).is_err());
+
+            // A length that would overflow when the head is added to it
+            let head = format!("POST /move HTTP/1.1\r\nContent-Length: {}\r\n\r\n", usize::MAX).into_bytes();
+            assert!(send(vec![head], Duration::ZERO, Duration::from_secs(5)).is_err());
+
+            let endless = vec![b"POST /move HTTP/1.1\r\n".to_vec(), vec![b'x'; MAX_REQUEST + 1]];
+            assert!(send(endless, Duration::ZERO, Duration::from_secs(5)).is_err());
+        }
+
+        #[test]
+        fn errors_from_the_server() {
+            let response = b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n";
+
+            assert!(matches!(parse_response(response, false), Some(Err(err)) if err.contains("500")));
+        }
+    }
+}
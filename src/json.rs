@@ -0,0 +1,330 @@
+pub mod json {
+    use std::fmt;
+
+    // Arrays and objects inside each other this deep are refused, rather than running out of stack on them
+    const MAX_DEPTH: usize = 64;
+
+    // Just enough JSON to talk to other snake programs
+    #[derive(Clone, PartialEq, Debug)]
+    pub enum Json {
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        Array(Vec<Json>),
+        // Keeps the keys in the order they were written
+        Object(Vec<(String, Json)>),
+    }
+
+    impl Json {
+        pub fn parse(text: &str) -> Result<Self, String> {
+            let mut parser = Parser { chars: text.chars().collect(), at: 0, depth: 0 };
+
+            let value = parser.value()?;
+            parser.skip_whitespace();
+
+            match parser.peek() {
+                None => Ok(value),
+                Some(c) => Err(format!("unexpected '{}' after the JSON value", c)),
+            }
+        }
+
+        pub fn object(fields: Vec<(&str, Json)>) -> Self {
+            Json::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+        }
+
+        pub fn get(&self, key: &str) -> Option<&Json> {
+            match self {
+                Json::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+                _ => None,
+            }
+        }
+
+        pub fn as_str(&self) -> Option<&str> {
+            match self {
+                Json::String(string) => Some(string),
+                _ => None,
+            }
+        }
+
+        pub fn as_array(&self) -> Option<&[Json]> {
+            match self {
+                Json::Array(items) => Some(items),
+                _ => None,
+            }
+        }
+
+        // Whole numbers that aren't negative, like coordinates and sizes
+        pub fn as_usize(&self) -> Option<usize> {
+            match self {
+                Json::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+                _ => None,
+            }
+        }
+    }
+
+    impl fmt::Display for Json {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Json::Null => write!(f, "null"),
+                Json::Bool(b) => write!(f, "{}", b),
+                Json::Number(n) => write!(f, "{}", n),
+                Json::String(string) => write_string(f, string),
+                Json::Array(items) => {
+                    write!(f, "[")?;
+                    for (i, item) in items.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ",")?;
+                        }
+                        write!(f, "{}", item)?;
+                    }
+                    write!(f, "]")
+                }
+                Json::Object(fields) => {
+                    write!(f, "{{")?;
+                    for (i, (key, value)) in fields.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ",")?;
+                        }
+                        write_string(f, key)?;
+                        write!(f, ":{}", value)?;
+                    }
+                    write!(f, "}}")
+                }
+            }
+        }
+    }
+
+    fn write_string(f: &mut fmt::Formatter, string: &str) -> fmt::Result {
+        write!(f, "\"")?;
+
+        for c in string.chars() {
+            match c {
+                '"' => write!(f, "\\\"")?,
+                '\\' => write!(f, "\\\\")?,
+                '\n' => write!(f, "\\n")?,
+                '\r' => write!(f, "\\r")?,
+                '\t' => write!(f, "\\t")?,
+                c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+
+        write!(f, "\"")
+    }
+
+    struct Parser {
+        chars: Vec<char>,
+        at: usize,
+        // How many arrays and objects the parser is inside of
+        depth: usize,
+    }
+
+    impl Parser {
+        fn peek(&self) -> Option<char> {
+            self.chars.get(self.at).copied()
+        }
+
+        fn next(&mut self) -> Option<char> {
+            let c = self.peek();
+            self.at += 1;
+            c
+        }
+
+        fn skip_whitespace(&mut self) {
+            while self.peek().is_some_and(|c| c.is_whitespace()) {
+                self.at += 1;
+            }
+        }
+
+        fn expect(&mut self, expected: char) -> Result<(), String> {
+            match self.next() {
+                Some(c) if c == expected => Ok(()),
+                Some(c) => Err(format!("expected '{}' but found '{}'", expected, c)),
+                None => Err(format!("expected '{}' but the JSON ended", expected)),
+            }
+        }
+
+        fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+            for expected in word.chars() {
+                self.expect(expected)?;
+            }
+
+            Ok(value)
+        }
+
+        fn value(&mut self) -> Result<Json, String> {
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(c @ ('{' | '[')) => {
+                    if self.depth == MAX_DEPTH {
+                        return Err(format!("the JSON is nested more than {} deep", MAX_DEPTH));
+                    }
+
+                    self.depth += 1;
+                    let value = match c {
+                        '{' => self.object(),
+                        _ => self.array(),
+                    };
+                    self.depth -= 1;
+
+                    value
+                }
+                Some('"') => Ok(Json::String(self.string()?)),
+                Some('t') => self.literal("true", Json::Bool(true)),
+                Some('f') => self.literal("false", Json::Bool(false)),
+                Some('n') => self.literal("null", Json::Null),
+                Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+                Some(c) => Err(format!("unexpected '{}' where a JSON value should be", c)),
+                None => Err("the JSON ended where a value should be".to_string()),
+            }
+        }
+
+        fn object(&mut self) -> Result<Json, String> {
+            self.expect('{')?;
+            let mut fields = Vec::new();
+
+            self.skip_whitespace();
+            if self.peek() == Some('}') {
+                self.at += 1;
+                return Ok(Json::Object(fields));
+            }
+
+            loop {
+                self.skip_whitespace();
+                let key = self.string()?;
+                self.skip_whitespace();
+                self.expect(':')?;
+                fields.push((key, self.value()?));
+
+                self.skip_whitespace();
+                match self.next() {
+                    Some(',') => {}
+                    Some('}') => return Ok(Json::Object(fields)),
+                    _ => return Err("expected ',' or '}' in an object".to_string()),
+                }
+            }
+        }
+
+        fn array(&mut self) -> Result<Json, String> {
+            self.expect('[')?;
+            let mut items = Vec::new();
+
+            self.skip_whitespace();
+            if self.peek() == Some(']') {
+                self.at += 1;
+                return Ok(Json::Array(items));
+            }
+
+            loop {
+                items.push(self.value()?);
+
+                self.skip_whitespace();
+                match self.next() {
+                    Some(',') => {}
+                    Some(']') => return Ok(Json::Array(items)),
+                    _ => return Err("expected ',' or ']' in an array".to_string()),
+                }
+            }
+        }
+
+        fn string(&mut self) -> Result<String, String> {
+            self.expect('"')?;
+            let mut string = String::new();
+
+            loop {
+                match self.next().ok_or("a string is missing its closing quote")? {
+                    '"' => return Ok(string),
+                    '\\' => {
+                        let c = match self.next().ok_or("a string ends in the middle of an escape")? {
+                            'n' => '\n',
+                            'r' => '\r',
+                            't' => '\t',
+                            'b' => '\u{8}',
+                            'f' => '\u{c}',
+                            'u' => {
+                                let hex: String = (0..4).filter_map(|_| self.next()).collect();
+                                // Characters outside the basic plane come as two escapes, which aren't worth putting back together here
+                                u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32).unwrap_or('\u{fffd}')
+                            }
+                            c => c,
+                        };
+                        string.push(c);
+                    }
+                    c => string.push(c),
+                }
+            }
+        }
+
+        fn number(&mut self) -> Result<Json, String> {
+            let start = self.at;
+            while self.peek().is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(c)) {
+                self.at += 1;
+            }
+
+            let text: String = self.chars[start..self.at].iter().collect();
+            text.parse().map(Json::Number).map_err(|_| format!("'{}' isn't a number", text))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn escapes_survive_a_round_trip() {
+            let string = Json::String("a \"quote\", a \\ and\na tab\t\u{1}".to_string());
+
+            assert_eq!(string.to_string(), r#""a \"quote\", a \\ and\na tab\t\u0001""#);
+            assert_eq!(Json::parse(&string.to_string()), Ok(string));
+        }
+
+        #[test]
+        fn unicode_escapes() {
+            assert_eq!(Json::parse(r#""caf\u00e9 \/""#), Ok(Json::String("café /".to_string())));
+            assert_eq!(Json::parse(r#""\ud83d""#), Ok(Json::String("\u{fffd}".to_string())));
+            assert!(Json::parse(r#""open"#).is_err());
+        }
+
+        #[test]
+        fn numbers() {
+            assert_eq!(Json::parse("12"), Ok(Json::Number(12.0)));
+            assert_eq!(Json::parse("-3.5"), Ok(Json::Number(-3.5)));
+            assert_eq!(Json::parse("1e3"), Ok(Json::Number(1000.0)));
+            assert_eq!(Json::parse(" 7 ").ok().and_then(|n| n.as_usize()), Some(7));
+            assert_eq!(Json::parse("-1").ok().and_then(|n| n.as_usize()), None);
+            assert_eq!(Json::parse("1.5").ok().and_then(|n| n.as_usize()), None);
+            assert!(Json::parse("1.2.3").is_err());
+            assert!(Json::parse("-").is_err());
+        }
+
+        #[test]
+        fn nesting() {
+            let json = Json::parse(r#" { "a" : [1, {"b": [true, false, null]}, []], "c": {} } "#).unwrap();
+
+            let a = json.get("a").and_then(|a| a.as_array()).unwrap();
+            assert_eq!(a.len(), 3);
+            assert_eq!(a[1].get("b"), Some(&Json::Array(vec![Json::Bool(true), Json::Bool(false), Json::Null])));
+            assert_eq!(json.get("c"), Some(&Json::Object(Vec::new())));
+            assert_eq!(Json::parse(&json.to_string()), Ok(json));
+        }
+
+        #[test]
+        fn broken_json() {
+            assert!(Json::parse("[1, 2").is_err());
+            assert!(Json::parse("{\"a\" 1}").is_err());
+            assert!(Json::parse("[1] 2").is_err());
+            assert!(Json::parse("").is_err());
+        }
+
+        #[test]
+        fn deep_nesting_is_refused() {
+            let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+
+            assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+            assert!(Json::parse(&nested(MAX_DEPTH + 1)).is_err());
+            assert!(Json::parse(&nested(100_000)).is_err());
+        }
+    }
+}
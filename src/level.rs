@@ -0,0 +1,156 @@
+pub mod level {
+    use std::fs;
+
+    use crate::snake::snake::*;
+
+    // A board drawn as text, one character per cell:
+    //
+    //   direction up
+    //   #########
+    //   #...*...#
+    //   #.......#
+    //   #...S...#
+    //   #########
+    //
+    // # is a wall, S is where the head of the snake starts, * is a place food can spawn and . or a space is empty.
+    // The optional first line says which way the snake starts moving, right when it is left out.
+    // The body trails behind the head, so it needs room in the opposite direction.
+    #[derive(Clone, Debug)]
+    pub struct Level {
+        pub grid: Grid,
+        pub start: Pos,
+        pub direction: Direction,
+        // Where food can spawn, anywhere when empty
+        pub food: Vec<Pos>,
+    }
+
+    impl Level {
+        // An empty board with the snake along the top row
+        pub fn new(grid: Grid, start_len: usize) -> Self {
+            Self {
+                grid,
+                start: Pos::new(start_len - 1, 0),
+                direction: Direction::Right,
+                food: Vec::new(),
+            }
+        }
+
+        pub fn load(path: &str) -> Result<Self, String> {
+            let text = fs::read_to_string(path).map_err(|err| format!("Couldn't read the level '{}': {}", path, err))?;
+
+            Self::from_text(&text).map_err(|err| format!("Invalid level '{}': {}", path, err))
+        }
+
+        pub fn save(&self, path: &str) -> Result<(), String> {
+            fs::write(path, self.to_text()).map_err(|err| format!("Couldn't save the level to '{}': {}", path, err))
+        }
+
+        pub fn to_text(&self) -> String {
+            let direction = match self.direction {
+                Direction::Up => "up",
+                Direction::Down => "down",
+                Direction::Left => "left",
+                Direction::Right => "right",
+            };
+
+            let mut text = format!("direction {}\n", direction);
+            for y in 0..self.grid.height {
+                for x in 0..self.grid.width {
+                    let pos = Pos::new(x, y);
+
+                    if pos == self.start {
+                        text.push('S');
+                    }
+                    else if self.grid.is_wall(&pos) {
+                        text.push('#');
+                    }
+                    else if self.food.contains(&pos) {
+                        text.push('*');
+                    }
+                    else {
+                        text.push('.');
+                    }
+                }
+                text.push('\n');
+            }
+
+            text
+        }
+
+        pub fn from_text(text: &str) -> Result<Self, String> {
+            let mut lines: Vec<&str> = text.lines().collect();
+            while lines.last().is_some_and(|line| line.trim().is_empty()) {
+                lines.pop();
+            }
+
+            let mut direction = Direction::Right;
+            if let Some(value) = lines.first().and_then(|line| line.strip_prefix("direction ")) {
+                direction = match value.trim() {
+                    "up" => Direction::Up,
+                    "down" => Direction::Down,
+                    "left" => Direction::Left,
+                    "right" => Direction::Right,
+                    _ => return Err(format!("bad direction '{}', expected up, down, left or right", value.trim())),
+                };
+                lines.remove(0);
+            }
+
+            let width = lines.first().map(|line| line.chars().count()).unwrap_or(0);
+            if lines.len() < 2 || width < 2 {
+                return Err("the board has to be at least 2x2".to_string());
+            }
+
+            let mut walls = Vec::new();
+            let mut starts = Vec::new();
+            let mut food = Vec::new();
+
+            for (y, line) in lines.iter().enumerate() {
+                if line.chars().count() != width {
+                    return Err(format!("row {} is {} cells wide but the first row is {}, the board has to be rectangular", y + 1, line.chars().count(), width));
+                }
+
+                for (x, c) in line.chars().enumerate() {
+                    match c {
+                        '#' => walls.push(Pos::new(x, y)),
+                        'S' => starts.push(Pos::new(x, y)),
+                        '*' => food.push(Pos::new(x, y)),
+                        '.' | ' ' => {}
+                        _ => return Err(format!("unknown cell '{}' in row {}, expected #, S, * or .", c, y + 1)),
+                    }
+                }
+            }
+
+            let start = match starts[..] {
+                [start] => start,
+                [] => return Err("there is no start, mark the head of the snake with S".to_string()),
+                _ => return Err(format!("there are {} starts, there can only be one S", starts.len())),
+            };
+
+            Ok(Self {
+                grid: Grid::new(width, lines.len()).with_walls(&walls),
+                start,
+                direction,
+                food,
+            })
+        }
+
+        // The starting body from the head back, trailing behind the start
+        pub fn body(&self, start_len: usize) -> Result<Vec<Pos>, String> {
+            let mut body = vec![self.start];
+
+            if self.grid.is_wall(&self.start) {
+                return Err(format!("the start at {},{} is inside a wall", self.start.x, self.start.y));
+            }
+
+            while body.len() < start_len {
+                let behind = body[body.len() - 1].adjacent(&self.direction.opposite(), &self.grid)
+                    .filter(|pos| !body.contains(pos))
+                    .ok_or(format!("there is no room for a snake of {} cells behind the start at {},{}", start_len, self.start.x, self.start.y))?;
+
+                body.push(behind);
+            }
+
+            Ok(body)
+        }
+    }
+}
@@ -0,0 +1,79 @@
+pub mod longest_path {
+    use crate::astar::astar::*;
+    use crate::game::game::*;
+    use crate::snake::snake::*;
+    use crate::strategy::strategy::*;
+
+    // Takes the long way around to the food, packing the body tightly so there is room left for later,
+    // and chases its own tail while the long way isn't safe
+    pub struct LongestPath {
+        astar: AStar,
+        path: Vec<Direction>,
+        path_index: usize,
+        search_every: usize,
+        score: i32
+    }
+
+    impl LongestPath {
+        pub fn new(search_every: usize) -> Self {
+            Self {
+                astar: AStar::new(Fallback::LongestPath, true, search_every),
+                path: Vec::new(),
+                path_index: 0,
+                search_every,
+                score: 0
+            }
+        }
+
+        fn path_found(&self) -> bool {
+            self.path_index < self.path.len()
+        }
+
+        fn path_blocked(&self, game: &Game) -> bool {
+            self.path_found() && game.snake.head().adjacent(&self.path[self.path_index], &game.grid).is_none()
+        }
+
+        fn search(&mut self, game: &Game) {
+            let snake = &game.snake;
+            let path = self.astar.longest_path(&game.grid, &snake.head(), &game.food.pieces, &snake.exclude_head());
+
+            self.path = match path {
+                Some(path) if self.astar.leaves_way_out(game, &path) => path,
+                _ => Vec::new(),
+            };
+            self.path_index = 0;
+        }
+    }
+
+    impl Strategy for LongestPath {
+        fn next_move(&mut self, game: &Game) -> Direction {
+            if game.score != self.score || (!self.path_found() && game.frame_count.is_multiple_of(self.search_every)) || self.path_blocked(game) {
+                self.search(game);
+                self.score = game.score;
+            }
+
+            if self.path_found() {
+                let dir = self.path[self.path_index];
+                self.path_index += 1;
+
+                dir
+            }
+            else {
+                self.astar.chase_tail(game).unwrap_or_else(|| game.snake.get_dir_of_free_space(&game.grid))
+            }
+        }
+
+        fn reset(&mut self, game: &Game) {
+            self.path = Vec::new();
+            self.path_index = 0;
+            self.score = game.score;
+        }
+
+        fn planned_path(&self) -> &[Direction] {
+            match self.path_found() {
+                true => &self.path[self.path_index..],
+                false => &[],
+            }
+        }
+    }
+}
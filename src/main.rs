@@ -1,177 +1,569 @@
+#![allow(clippy::module_inception)]
+
+use std::time::Duration;
+
 use raylib::prelude::*;
+use rand::Rng;
 
 mod snake;
 use snake::snake::*;
 
+mod game;
+use game::game::*;
+
+mod render;
+use render::render::*;
+
 mod astar;
-use astar::astar::*;
 
 mod ham_cycle;
-use ham_cycle::ham_cycle::*;
+
+mod longest_path;
 
 mod gui;
 use gui::gui::*;
 
+mod strategy;
+use strategy::strategy::*;
+
+mod cli;
+use cli::cli::*;
+
+mod bench;
+use bench::bench::*;
+
+mod replay;
+use replay::replay::*;
+
+mod terminal;
+
+mod settings;
+use settings::settings::*;
+
+mod level;
+use level::level::*;
+
+mod editor;
+use editor::editor::*;
+
+mod arena;
+use arena::arena::*;
+
+mod json;
+
+mod http;
+
+mod battlesnake;
+use battlesnake::battlesnake::*;
+
+mod pipe;
+
 const CELL_SIZE: usize = 18;
-const CELL_SIZE_I: i32 = CELL_SIZE as i32;
 
 const GRID_WIDTH: usize = 50;
 const GRID_HEIGHT: usize = 50;
 
-const SCREEN_WIDTH: i32 = GRID_WIDTH as i32 * CELL_SIZE_I;
-const SCREEN_HEIGHT: i32 = GRID_HEIGHT as i32 * CELL_SIZE_I;
-
 const GAME_SPEED: usize = 10;
 const SEARCH_EVERY: usize = 10;
 
 const START_LEN: usize = 3;
+const FOOD_COUNT: usize = 1;
+
+const MAX_SNAKES: usize = 8;
+// Wins it takes to take a match between several snakes
+const ROUNDS: usize = 3;
+// Frames the result of a round stays on screen before the next one starts
+const ROUND_PAUSE: usize = 120;
+
+// Milliseconds a bot gets to answer a move
+const BOT_TIMEOUT: u64 = 500;
+// Where serve listens when no --listen is given
+const SERVE_ADDRESS: &str = "127.0.0.1:8000";
+// Seconds without a request before serve forgets a game, for games whose /end never comes
+const SERVE_FORGET_AFTER: u64 = 60;
+// The most cells a side of a board from a Battlesnake request may have, like on the Battlesnake servers
+const MAX_BOARD: usize = 255;
+
+const BENCH_GAMES: usize = 100;
+// Grid sizes worth of moves without eating before a benchmarked game is given up on
+const STALL_AFTER: usize = 2;
+
+// Random spanning trees to try before giving up on a Hamiltonian cycle that fits the snake
+const CYCLE_ATTEMPTS: usize = 20;
+// The Hamiltonian cycle stops taking shortcuts once the snake fills this much of the board
+const SHORTCUTS_UNTIL: f32 = 0.5;
 
 const FPS: u32 = 60;
 
 const FONT_SIZE: i32 = 40;
 
-const BUTTON_WIDTH: f32 = SCREEN_WIDTH as f32 / 2.5;
-const BUTTON_HEIGHT: f32 = SCREEN_HEIGHT as f32 / 6.0;
-const BUTTON_FONT_SIZE: i32 = SCREEN_HEIGHT / 15;
+// Where the editor saves and loads levels when no --level is given
+const LEVEL_FILE: &str = "snake.level";
 
-fn draw(d: &mut RaylibDrawHandle, snake: &Snake, food: &Food, frame_count: &usize, score: &i32) {
-    d.clear_background(Color::LIGHTGRAY);
+// Relative to the size of the screen
+const BUTTON_WIDTH: f32 = 1.0 / 2.5;
+const BUTTON_HEIGHT: f32 = 1.0 / 6.0;
+const BUTTON_FONT_SIZE: f32 = 1.0 / 15.0;
 
-    food.draw(d);
-    snake.draw(d);
+fn main() {
+    // ==================================
+    // Initialize variables
+    // ==================================
+    // A broken settings file shouldn't keep the game from starting
+    let mut settings = Settings::load().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        Settings::new()
+    });
 
-    let score_text = &format!("Score: {}", score);
-    let score_text_length = measure_text(score_text, FONT_SIZE);
+    let args = match Args::parse(&settings) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
 
-    let frame_text = &format!("Frames: {}", frame_count);
-    let frame_text_length = measure_text(frame_text, 20);
+    if args.command == Command::Help {
+        println!("{}", USAGE);
+        return;
+    }
 
-    d.draw_text(score_text, SCREEN_WIDTH / 2 - score_text_length / 2, 10, FONT_SIZE, Color::YELLOW);
-    d.draw_text(frame_text, SCREEN_WIDTH / 2 - frame_text_length / 2, SCREEN_HEIGHT - 30, 20, Color::BROWN);
+    let mut level = match &args.level {
+        Some(path) => match Level::load(path) {
+            Ok(level) => level,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        },
+        None => Level::new(Grid::new(args.width, args.height).with_walls(&args.walls), args.start_len),
+    };
+    level.grid.wrap = args.wrap;
 
-    d.draw_fps(10, 10);
+    let start = match level.body(args.start_len) {
+        Ok(start) => start,
+        Err(err) => {
+            eprintln!("Can't start the level: {}", err);
+            return;
+        }
+    };
 
-    if snake.game_over {
-        let text = "GAME OVER";
-        let text_length = measure_text(text, FONT_SIZE);
-        d.draw_text(text, SCREEN_WIDTH / 2 - text_length / 2, SCREEN_WIDTH / 2 - text_length / 2, FONT_SIZE, Color::RED);
-    }
-}
+    let seed = args.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let mut cell_size = args.cell_size as i32;
+    let mut speed = args.game_speed;
+    let mut food = args.food;
+    let mut options = StrategyOptions {
+        fallback: args.fallback,
+        search_every: args.search_every,
+        bots: args.bots.clone(),
+        timeout: Duration::from_millis(args.timeout),
+    };
 
-fn main() {
     // ==================================
-    // Initialize variables
+    // Benchmark without a window
     // ==================================
-    let (mut rl, thread) = raylib::init()
-        .size(SCREEN_WIDTH, SCREEN_HEIGHT)
-        .title("Snake")
-        .build();
+    if args.command == Command::Bench {
+        let mode = args.mode.expect("bench always has a mode");
+        let mut game = Game::new(level.grid, start, level.food, args.food, seed);
 
-    let mut astar_random = false;
+        let name = match mode {
+            Mode::AStar | Mode::AStarSafe => format!("{:?} ({:?})", mode, args.fallback),
+            _ => format!("{:?}", mode),
+        };
+        println!("Running {} games of {} on a {}x{} grid with seed {}", args.games, name, game.grid.width, game.grid.height, seed);
 
-    let mode = mode_menu(&mut rl, &thread);
+        let results = match run_bench(mode, &options, args.games, &mut game, args.replay.as_deref()) {
+            Ok(results) => results,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        };
 
-    if mode == "" {
+        print_report(&results);
         return;
     }
-    else if mode == "self" {
-        rl.set_target_fps(FPS);
-    }
-    else if mode == "a*" {
-        astar_random = match astar_random_menu(&mut rl, &thread) {
-            Some(x) => x,
-            None => return,
+
+    // ==================================
+    // Play as a Battlesnake bot
+    // ==================================
+    if args.command == Command::Serve {
+        let mode = args.mode.expect("serve always has a mode");
+        let mut server = Server::new(mode, options, settings.colors.body, seed);
+
+        if let Err(err) = server.serve(&args.listen) {
+            eprintln!("{}", err);
         }
+
+        return;
     }
 
-    let mut frame_count = 0;
-    
-    let mut snake = Snake::new();
-    let mut food = Food::new();
+    // ==================================
+    // Watch a saved game
+    // ==================================
+    if args.command == Command::Replay {
+        let path = args.replay.expect("replay always has a path");
+        let replay = match Replay::load(&path) {
+            Ok(replay) => replay,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        };
+
+        let (mut rl, thread) = raylib::init()
+            .size(replay.grid.width as i32 * cell_size, replay.grid.height as i32 * cell_size)
+            .title("Snake replay")
+            .build();
 
-    let mut score = 0;
+        watch_replay(&mut rl, &thread, &replay, &settings.colors);
+        return;
+    }
 
     // ==================================
-    // Play self
+    // Several snakes on one board
     // ==================================
-    if mode == "self" {
-        while !rl.window_should_close() {
-            snake.get_inputs(&rl);
+    if !args.snakes.is_empty() {
+        let mut arena = match Arena::new(level.grid, &args.snakes, &options, args.start_len, args.food, seed) {
+            Ok(arena) => arena,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        };
+
+        if args.output == Output::Headless {
+            let mut round = 1;
+
+            loop {
+                let stalled = run_arena(&mut arena);
+                let (moves, seed) = (arena.frame_count, arena.seed);
+
+                match (arena.end_round(), stalled) {
+                    (Some(i), _) => println!("Round {}: snake {} wins after {} moves (seed {})", round, i + 1, moves, seed),
+                    (None, true) => println!("Round {}: stalled after {} moves (seed {})", round, moves, seed),
+                    (None, false) => println!("Round {}: draw after {} moves (seed {})", round, moves, seed),
+                }
+
+                if arena.champion(args.rounds).is_some() {
+                    break;
+                }
 
-            if !snake.game_ended() && frame_count % GAME_SPEED == 0 {
-                snake.update(&mut food, &mut score);
+                arena.reset();
+                round += 1;
             }
-    
-            if rl.is_key_pressed(consts::KeyboardKey::KEY_R) {
-                snake.reset();
-                food.respawn(&snake);
-    
-                frame_count = 0;
-                score = 0;
+
+            for (line, _) in standings(&arena, &settings.colors) {
+                println!("{}", line);
             }
-            
-            frame_count += 1;
-    
-            let mut d = rl.begin_drawing(&thread);
-            draw(&mut d, &snake, &food, &frame_count, &score);
         }
+        else {
+            let (mut rl, thread) = raylib::init()
+                .size(arena.grid.width as i32 * cell_size, arena.grid.height as i32 * cell_size)
+                .title("Snake arena")
+                .build();
+
+            play_arena(&mut rl, &thread, &mut arena, speed, args.rounds, &settings);
+        }
+
+        return;
     }
+
     // ==================================
-    // A* algorithm
+    // Play a single game without a window
     // ==================================
-    else if mode == "a*" {
-        let mut astar = AStar::new(astar_random);
-        astar.search(&snake, &food);
-
-        while !rl.window_should_close() {
-            if !snake.game_ended() {
-                astar.update(&mut snake, &mut food, &frame_count, &mut score);
-                
-                frame_count += 1;
+    if args.output != Output::Gui {
+        let mode = args.mode.unwrap_or(Mode::Play);
+        let mut game = Game::new(level.grid, start, level.food, args.food, seed);
+
+        let mut strategy = match create_strategy(mode, &options, &game) {
+            Ok(strategy) => strategy,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        };
+
+        if args.output == Output::Terminal {
+            if let Err(err) = terminal::terminal::run(&mut game, strategy.as_mut(), game_speed(mode, speed), &settings) {
+                eprintln!("{}", err);
+            }
+        }
+        else {
+            let result = run_game(&mut game, strategy.as_mut());
+            let outcome = match (result.win, result.stalled) {
+                (true, _) => "won",
+                (_, true) => "stalled",
+                _ => "game over",
+            };
+
+            println!("Score {} in {} moves, {} (seed {}, {:.2?})", result.score, result.moves, outcome, result.seed, result.time);
+        }
+
+        return;
+    }
+
+    let (mut rl, thread) = raylib::init()
+        .size(level.grid.width as i32 * cell_size, level.grid.height as i32 * cell_size)
+        .title("Snake")
+        .build();
+
+    let mut rounds = args.rounds;
+    // Loaded and edited levels keep their own size
+    let mut own_level = args.level.is_some();
+
+    // Go straight into the game when the mode was picked on the command line
+    let mode = match args.mode {
+        Some(mode) => mode,
+        None => {
+            let mode = loop {
+                match mode_menu(&mut rl, &thread) {
+                    Some(MenuChoice::Mode(mode)) => break mode,
+                    Some(MenuChoice::Settings) => {
+                        if settings_menu(&mut rl, &thread, &mut settings) {
+                            // Walls that don't fit on the new board are left out
+                            if !own_level {
+                                let walls: Vec<Pos> = args.walls.iter().copied().filter(|pos| pos.x < settings.width && pos.y < settings.height).collect();
+                                let grid = Grid::new(settings.width, settings.height).with_walls(&walls);
+                                level = Level::new(grid, args.start_len.min(settings.width));
+                            }
+                            level.grid.wrap = settings.wrap;
+                            cell_size = settings.cell_size as i32;
+                            speed = settings.speed;
+                            food = settings.food;
+                            rounds = settings.rounds;
+                            options.fallback = settings.fallback;
+
+                            rl.set_window_size(level.grid.width as i32 * cell_size, level.grid.height as i32 * cell_size);
+                        }
+                    }
+                    Some(MenuChoice::TwoPlayers) => {
+                        let start_len = args.start_len.min(level.grid.width);
+
+                        match Arena::new(level.grid.clone(), &[Mode::Play, Mode::Play], &options, start_len, food, seed) {
+                            Ok(mut arena) => play_arena(&mut rl, &thread, &mut arena, speed, rounds, &settings),
+                            Err(err) => eprintln!("{}", err),
+                        }
+                    }
+                    Some(MenuChoice::Editor) => {
+                        let path = args.level.as_deref().unwrap_or(LEVEL_FILE);
+                        let play = level_editor(&mut rl, &thread, &mut level, args.start_len, path, &settings.colors);
+                        own_level = true;
+
+                        // Loading a level in the editor can change the size of the board
+                        rl.set_window_size(level.grid.width as i32 * cell_size, level.grid.height as i32 * cell_size);
+
+                        if play {
+                            break Mode::Play;
+                        }
+                    }
+                    None => return,
+                }
+            };
+
+            if mode == Mode::AStar {
+                options.fallback = match astar_fallback_menu(&mut rl, &thread) {
+                    Some(fallback) => fallback,
+                    None => return,
+                };
+
+                match astar_safe_menu(&mut rl, &thread) {
+                    Some(true) => Mode::AStarSafe,
+                    Some(false) => Mode::AStar,
+                    None => return,
+                }
+            }
+            else if mode == Mode::Hamiltonian {
+                match ham_shortcut_menu(&mut rl, &thread) {
+                    Some(true) => Mode::HamiltonianShortcuts,
+                    Some(false) => Mode::Hamiltonian,
+                    None => return,
+                }
             }
-    
-            if rl.is_key_pressed(consts::KeyboardKey::KEY_R) {
-                snake.reset();
-                food.respawn(&snake);
-
-                astar.reset();
-                astar.search(&snake, &food);
-    
-                frame_count = 0;
-                score = 0;
+            else {
+                mode
             }
-    
-            let mut d = rl.begin_drawing(&thread);
-            astar.draw_path(&mut d, &snake);
-            draw(&mut d, &snake, &food, &frame_count, &score);
         }
+    };
+
+    if mode == Mode::Play {
+        rl.set_target_fps(FPS);
     }
+
+    // The board might have been made smaller than the snake on the settings screen
+    let start = match level.body(args.start_len.min(level.grid.width)) {
+        Ok(start) => start,
+        Err(err) => {
+            eprintln!("Can't start the level: {}", err);
+            return;
+        }
+    };
+    let mut game = Game::new(level.grid, start, level.food, food, seed);
+
+    let mut strategy = match create_strategy(mode, &options, &game) {
+        Ok(strategy) => strategy,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    let game_speed = game_speed(mode, speed);
+
+    let mut frames = 0;
+
     // ==================================
-    // Hamiltonian cycle
+    // Game loop
     // ==================================
-    else if mode == "ham" {
-        let mut ham = HamiltonianCycle::new();
-        ham.generate(&snake);
-
-        while !rl.window_should_close() {
-            if !snake.game_ended() {
-                ham.update(&mut snake, &mut food, &mut score);
-                
-                frame_count += 1;
+    while !rl.window_should_close() {
+        if let Some(dir) = get_inputs(&rl, &settings.keys) {
+            strategy.on_key(dir);
+        }
+
+        if frames % game_speed == 0 {
+            game.step_with(strategy.as_mut());
+        }
+
+        if rl.is_key_pressed(consts::KeyboardKey::KEY_R) {
+            game.reset();
+            strategy.reset(&game);
+            frames = 0;
+        }
+
+        if rl.is_key_pressed(consts::KeyboardKey::KEY_S) {
+            let path = format!("snake-{}.replay", game.seed);
+
+            match game.replay.save(&path) {
+                Ok(()) => println!("Saved the replay to {}", path),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+
+        frames += 1;
+
+        let mut d = rl.begin_drawing(&thread);
+        d.clear_background(to_color(settings.colors.background));
+        draw_path(&mut d, strategy.planned_path(), &game, &settings.colors);
+        draw(&mut d, &game, &settings.colors);
+    }
+}
+
+// Only the player is slowed down, the algorithms move every frame
+fn game_speed(mode: Mode, speed: usize) -> usize {
+    match mode {
+        Mode::Play => speed,
+        _ => 1,
+    }
+}
+
+// All the snakes move together, at the speed of the players when one of them is steered by hand.
+// Every player has their own keys, and the match goes on until a snake has won enough rounds. R starts the round over.
+fn play_arena(rl: &mut RaylibHandle, thread: &RaylibThread, arena: &mut Arena, speed: usize, rounds: usize, settings: &Settings) {
+    let game_speed = match arena.players.iter().any(|player| player.mode == Mode::Play) {
+        true => game_speed(Mode::Play, speed),
+        false => 1,
+    };
+
+    rl.set_target_fps(FPS);
+    let mut frames = 0;
+    // Frames since the round ended
+    let mut pause = 0;
+
+    while !rl.window_should_close() {
+        for (human, keys) in settings.player_keys().into_iter().enumerate() {
+            if let Some(dir) = get_inputs(rl, keys) {
+                arena.on_key(human, dir);
+            }
+        }
+
+        if frames % game_speed == 0 {
+            arena.step();
+        }
+
+        // The round is scored once, and its result stays up for a moment before the next one starts
+        if arena.game_ended() {
+            if pause == 0 {
+                arena.end_round();
             }
-    
-            if rl.is_key_pressed(consts::KeyboardKey::KEY_R) {
-                snake.reset();
-                food.respawn(&snake);
-
-                ham.reset(&snake);
-    
-                frame_count = 0;
-                score = 0;
+            pause += 1;
+
+            if pause > ROUND_PAUSE {
+                if let Some(champion) = arena.champion(rounds) {
+                    let title = format!("Snake {} wins the match", champion + 1);
+
+                    match results_menu(rl, thread, &title, &standings(arena, &settings.colors)) {
+                        Some(true) => arena.new_match(),
+                        _ => return,
+                    }
+                }
+                else {
+                    arena.reset();
+                }
+
+                frames = 0;
+                pause = 0;
             }
-    
-            let mut d = rl.begin_drawing(&thread);
-            draw(&mut d, &snake, &food, &frame_count, &score);
         }
+
+        if rl.is_key_pressed(consts::KeyboardKey::KEY_R) {
+            arena.reset();
+            frames = 0;
+            pause = 0;
+        }
+
+        frames += 1;
+
+        let mut d = rl.begin_drawing(thread);
+        d.clear_background(to_color(settings.colors.background));
+        draw_arena(&mut d, arena, &settings.colors);
+    }
+}
+
+// A line for every snake with the rounds it won, most wins first
+fn standings(arena: &Arena, colors: &Colors) -> Vec<(String, Color)> {
+    let mut order: Vec<usize> = (0..arena.players.len()).collect();
+    order.sort_by_key(|i| std::cmp::Reverse(arena.players[*i].wins));
+
+    order.into_iter().map(|i| {
+        let player = &arena.players[i];
+        let line = format!("Snake {} ({}): {} wins", i + 1, player.mode.name(), player.wins);
+
+        (line, to_color(colors.for_snake(i).head))
+    }).collect()
+}
+
+// Space pauses, the arrow keys step forward and back while paused and R starts over
+fn watch_replay(rl: &mut RaylibHandle, thread: &RaylibThread, replay: &Replay, colors: &Colors) {
+    rl.set_target_fps(FPS);
+
+    let total = replay.moves.len();
+    let mut moves = 0;
+    let mut paused = false;
+    let mut game = replay.play(moves);
+
+    while !rl.window_should_close() {
+        if rl.is_key_pressed(consts::KeyboardKey::KEY_SPACE) {
+            paused = !paused;
+        }
+
+        if rl.is_key_pressed(consts::KeyboardKey::KEY_R) {
+            moves = 0;
+            game = replay.play(moves);
+        }
+        else if rl.is_key_pressed(consts::KeyboardKey::KEY_LEFT) && moves > 0 {
+            // Going back means playing the game again from the start
+            paused = true;
+            moves -= 1;
+            game = replay.play(moves);
+        }
+        else if (!paused || rl.is_key_pressed(consts::KeyboardKey::KEY_RIGHT)) && moves < total {
+            replay.advance(&mut game, moves);
+            moves += 1;
+        }
+
+        let mut d = rl.begin_drawing(thread);
+        d.clear_background(to_color(colors.background));
+        draw(&mut d, &game, colors);
+        draw_replay_status(&mut d, moves, total, paused);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,308 @@
+pub mod pipe {
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::{Child, ChildStdin, Command, Stdio};
+    use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use crate::battlesnake::battlesnake::direction_name;
+    use crate::game::game::*;
+    use crate::snake::snake::*;
+    use crate::strategy::strategy::*;
+
+    fn cells(positions: &[Pos]) -> String {
+        positions.iter().map(|pos| format!(" {},{}", pos.x, pos.y)).collect()
+    }
+
+    // What the bot is sent every move, all on one line:
+    //
+    //   20 20 body 3,0 2,0 1,0 food 7,4 walls 5,5 snake 10,10 10,11
+    //
+    // The width and height of the board come first, then every list of cells after its name. Cells count from the top left
+    // like everywhere else, the body goes from the head back and every other snake on the board gets a list of its own.
+    pub fn board_line(game: &Game) -> String {
+        let mut line = format!("{} {} body{} food{} walls{}", game.grid.width, game.grid.height, cells(&game.snake.body), cells(&game.food.pieces), cells(&game.fixed_walls()));
+        for other in game.others.iter() {
+            line += &format!(" snake{}", cells(&other.body));
+        }
+
+        line
+    }
+
+    fn parse_move(line: &str) -> Result<Direction, String> {
+        match line.trim().to_ascii_uppercase().as_str() {
+            "U" => Ok(Direction::Up),
+            "D" => Ok(Direction::Down),
+            "L" => Ok(Direction::Left),
+            "R" => Ok(Direction::Right),
+            _ => Err(format!("'{}' isn't a move, expected U, D, L or R", line.trim())),
+        }
+    }
+
+    enum BotError {
+        // The bot was too slow for this move, but may still answer the next one
+        Timeout,
+        // The bot is no use for the rest of the game
+        Failed(String),
+    }
+
+    struct Running {
+        child: Child,
+        stdin: ChildStdin,
+        // The lines the bot writes, read on their own thread so a bot that hangs can be given up on
+        lines: Receiver<String>,
+    }
+
+    // A snake steered by a program that reads the board from stdin and answers every line with a move on stdout.
+    // The program starts with the first move of a game, and its stdin is closed once the game is over.
+    // A bot that doesn't answer in time keeps going the way it was, and one that crashes does for the rest of the game.
+    pub struct PipeBot {
+        command: Vec<String>,
+        timeout: Duration,
+        running: Option<Running>,
+        crashed: bool,
+    }
+
+    impl PipeBot {
+        // The command is split on spaces, with the program first
+        pub fn new(command: &str, timeout: Duration) -> Result<Self, String> {
+            let command: Vec<String> = command.split_whitespace().map(|part| part.to_string()).collect();
+
+            if command.is_empty() {
+                return Err("The bot command is empty".to_string());
+            }
+
+            Ok(Self {
+                command,
+                timeout,
+                running: None,
+                crashed: false,
+            })
+        }
+
+        fn start(&self) -> Result<Running, String> {
+            let mut child = Command::new(&self.command[0])
+                .args(&self.command[1..])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|err| format!("couldn't start: {}", err))?;
+
+            let stdin = child.stdin.take().expect("The bot was started with a piped stdin");
+            let stdout = child.stdout.take().expect("The bot was started with a piped stdout");
+
+            let (sender, lines) = mpsc::channel();
+            thread::spawn(move || {
+                for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+
+            Ok(Running { child, stdin, lines })
+        }
+
+        // Close the stdin of the bot and give it the timeout to finish up, it is killed when it is still running after that
+        fn stop(&mut self) {
+            if let Some(Running { mut child, stdin, .. }) = self.running.take() {
+                drop(stdin);
+
+                let deadline = Instant::now() + self.timeout;
+                while let Ok(None) = child.try_wait() {
+                    if Instant::now() >= deadline {
+                        let _ = child.kill();
+                        break;
+                    }
+
+                    thread::sleep(Duration::from_millis(5));
+                }
+                let _ = child.wait();
+            }
+        }
+
+        fn ask(&mut self, game: &Game) -> Result<Direction, BotError> {
+            if self.running.is_none() {
+                self.running = Some(self.start().map_err(BotError::Failed)?);
+            }
+
+            let running = self.running.as_mut().expect("The bot was just started");
+
+            // An answer that came in too late for an earlier move would be taken as the answer to this one
+            while running.lines.try_recv().is_ok() {}
+
+            writeln!(running.stdin, "{}", board_line(game))
+                .and_then(|_| running.stdin.flush())
+                .map_err(|err| BotError::Failed(format!("couldn't be sent the board: {}", err)))?;
+
+            match running.lines.recv_timeout(self.timeout) {
+                Ok(line) => parse_move(&line).map_err(BotError::Failed),
+                Err(RecvTimeoutError::Timeout) => Err(BotError::Timeout),
+                Err(RecvTimeoutError::Disconnected) => match running.child.try_wait() {
+                    Ok(Some(status)) => Err(BotError::Failed(format!("exited with {}", status))),
+                    _ => Err(BotError::Failed("closed its stdout".to_string())),
+                },
+            }
+        }
+    }
+
+    impl Strategy for PipeBot {
+        fn next_move(&mut self, game: &Game) -> Direction {
+            if self.crashed {
+                return game.snake.direction();
+            }
+
+            let dir = game.snake.direction();
+
+            // Only a slow bot gets another chance, anything else is the end of it for this game
+            match self.ask(game) {
+                Ok(dir) => dir,
+                Err(BotError::Timeout) => {
+                    eprintln!("Bot '{}' timed out, moving {} instead", self.command.join(" "), direction_name(dir));
+                    dir
+                }
+                Err(BotError::Failed(err)) => {
+                    self.crashed = true;
+                    self.stop();
+                    eprintln!("Bot '{}' {}, moving {} for the rest of the game", self.command.join(" "), err, direction_name(dir));
+                    dir
+                }
+            }
+        }
+
+        fn reset(&mut self, _game: &Game) {
+            self.stop();
+            self.crashed = false;
+        }
+
+        fn end(&mut self, _game: &Game) {
+            self.stop();
+        }
+    }
+
+    impl Drop for PipeBot {
+        fn drop(&mut self) {
+            self.stop();
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // A bot running a shell script, which can't be passed through new since the command is split on spaces
+        fn script_bot(script: &str, timeout: Duration) -> PipeBot {
+            PipeBot {
+                command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+                timeout,
+                running: None,
+                crashed: false,
+            }
+        }
+
+        fn game() -> Game {
+            let mut grid = Grid::new(6, 4);
+            grid.set_wall(&Pos::new(5, 3), true);
+
+            let mut game = Game::new(grid, vec![Pos::new(2, 0), Pos::new(1, 0), Pos::new(0, 0)], Vec::new(), 1, 1);
+            game.food.pieces = vec![Pos::new(4, 1)];
+            game
+        }
+
+        #[test]
+        fn board_lines() {
+            let mut game = game();
+            assert_eq!(board_line(&game), "6 4 body 2,0 1,0 0,0 food 4,1 walls 5,3");
+
+            let other = Snake::from_body(&game.grid, vec![Pos::new(1, 2), Pos::new(0, 2)]);
+            other.body.iter().for_each(|pos| game.grid.set_wall(pos, true));
+            game.others.push(other);
+            assert_eq!(board_line(&game), "6 4 body 2,0 1,0 0,0 food 4,1 walls 5,3 snake 1,2 0,2");
+        }
+
+        #[test]
+        fn moves() {
+            assert_eq!(parse_move("U"), Ok(Direction::Up));
+            assert_eq!(parse_move(" d\r"), Ok(Direction::Down));
+            assert_eq!(parse_move("L"), Ok(Direction::Left));
+            assert_eq!(parse_move("r"), Ok(Direction::Right));
+            assert!(parse_move("up").is_err());
+            assert!(parse_move("").is_err());
+        }
+
+        #[test]
+        fn bots_answer_with_a_move() {
+            let mut bot = script_bot("while read line; do echo D; done", Duration::from_secs(5));
+            let game = game();
+
+            assert!(matches!(bot.ask(&game), Ok(Direction::Down)));
+            assert!(matches!(bot.ask(&game), Ok(Direction::Down)));
+            assert_eq!(bot.next_move(&game), Direction::Down);
+        }
+
+        #[test]
+        fn slow_bots_time_out() {
+            let mut bot = script_bot("read line; sleep 5; echo U", Duration::from_millis(100));
+            let game = game();
+
+            assert!(matches!(bot.ask(&game), Err(BotError::Timeout)));
+
+            // Only a move, the bot gets asked again next time
+            assert_eq!(bot.next_move(&game), Direction::Right);
+            assert!(!bot.crashed);
+        }
+
+        #[test]
+        fn bots_that_exit_fail() {
+            let mut bot = script_bot("read line; exit 3", Duration::from_secs(5));
+            assert!(matches!(bot.ask(&game()), Err(BotError::Failed(_))));
+
+            let mut bot = script_bot("read line; echo sideways", Duration::from_secs(5));
+            assert!(matches!(bot.ask(&game()), Err(BotError::Failed(_))));
+        }
+
+        #[test]
+        fn bots_that_fail_keep_going_for_the_rest_of_the_game() {
+            let mut bot = script_bot("exit 1", Duration::from_secs(5));
+            let game = game();
+
+            assert_eq!(bot.next_move(&game), Direction::Right);
+            assert!(bot.crashed && bot.running.is_none());
+
+            bot.reset(&game);
+            assert!(!bot.crashed);
+        }
+
+        #[test]
+        fn programs_that_dont_exist_fail() {
+            let mut bot = PipeBot::new("rust-snake-no-such-bot", Duration::from_secs(5)).unwrap();
+            assert!(matches!(bot.ask(&game()), Err(BotError::Failed(_))));
+
+            assert!(PipeBot::new("  ", Duration::from_secs(5)).is_err());
+        }
+
+        #[test]
+        fn stopping_does_not_hang() {
+            let game = game();
+
+            // A bot that exits once its stdin is closed is done well before the timeout
+            let mut bot = script_bot("while read line; do echo U; done", Duration::from_secs(5));
+            bot.ask(&game).ok();
+            let start = Instant::now();
+            bot.stop();
+            assert!(start.elapsed() < Duration::from_secs(2));
+            assert!(bot.running.is_none());
+
+            // One that never stops is killed after the timeout
+            let mut bot = script_bot("while true; do sleep 0.05; done", Duration::from_millis(200));
+            bot.ask(&game).ok();
+            let start = Instant::now();
+            bot.stop();
+            assert!(start.elapsed() < Duration::from_secs(2));
+            assert!(bot.running.is_none());
+
+            // Stopping a bot that never started does nothing
+            bot.stop();
+        }
+    }
+}
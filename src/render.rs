@@ -0,0 +1,158 @@
+pub mod render {
+    use raylib::prelude::*;
+    use raylib::core::input::key_from_i32;
+
+    use crate::arena::arena::*;
+    use crate::game::game::*;
+    use crate::settings::settings::*;
+    use crate::snake::snake::*;
+    use crate::FONT_SIZE;
+
+    pub fn get_inputs(handle: &RaylibHandle, keys: &KeyBindings) -> Option<Direction> {
+        [Direction::Up, Direction::Down, Direction::Left, Direction::Right].into_iter().find(|dir| {
+            match key_code(keys.get(*dir)).and_then(key_from_i32) {
+                Some(key) => handle.is_key_down(key),
+                None => false,
+            }
+        })
+    }
+
+    pub fn to_color(rgb: Rgb) -> Color {
+        Color::new(rgb.0, rgb.1, rgb.2, 255)
+    }
+
+    // The window is sized to fit the grid, so the cells fill it exactly
+    pub fn cell_size(d: &RaylibDrawHandle, grid: &Grid) -> i32 {
+        d.get_screen_width() / grid.width as i32
+    }
+
+    pub fn draw_cell(d: &mut RaylibDrawHandle, pos: &Pos, cell: i32, color: Color) {
+        let x = pos.x as i32 * cell;
+        let y = pos.y as i32 * cell;
+
+        d.draw_rectangle(x, y, cell, cell, color);
+    }
+
+    pub fn draw_snake(d: &mut RaylibDrawHandle, snake: &Snake, cell: i32, colors: &Colors) {
+        for (i, part) in snake.body.iter().enumerate() {
+            let color = match i {
+                0 => colors.head,
+                _ if i % 2 == 1 => colors.body,
+                _ => colors.body_alt,
+            };
+
+            draw_cell(d, part, cell, to_color(color));
+        }
+    }
+
+    pub fn draw_walls(d: &mut RaylibDrawHandle, grid: &Grid, cell: i32, colors: &Colors) {
+        for wall in grid.walls().iter() {
+            draw_cell(d, wall, cell, to_color(colors.wall));
+        }
+    }
+
+    pub fn draw_food(d: &mut RaylibDrawHandle, food: &Food, cell: i32, colors: &Colors) {
+        for pos in food.pieces.iter() {
+            draw_cell(d, pos, cell, to_color(colors.food));
+        }
+    }
+
+    pub fn draw_path(d: &mut RaylibDrawHandle, path: &[Direction], game: &Game, colors: &Colors) {
+        let cell = cell_size(d, &game.grid);
+
+        let mut cur = game.snake.head();
+        for dir in path.iter() {
+            cur = cur.adjacent(dir, &game.grid).expect("The path stays on the grid");
+            draw_cell(d, &cur, cell, to_color(colors.path));
+        }
+    }
+
+    pub fn draw(d: &mut RaylibDrawHandle, game: &Game, colors: &Colors) {
+        let cell = cell_size(d, &game.grid);
+
+        draw_walls(d, &game.grid, cell, colors);
+        draw_food(d, &game.food, cell, colors);
+        draw_snake(d, &game.snake, cell, colors);
+
+        let screen_width = d.get_screen_width();
+        let screen_height = d.get_screen_height();
+
+        let score_text = &format!("Score: {}", game.score);
+        let score_text_length = measure_text(score_text, FONT_SIZE);
+
+        let frame_text = &format!("Frames: {}", game.frame_count);
+        let frame_text_length = measure_text(frame_text, 20);
+
+        d.draw_text(score_text, screen_width / 2 - score_text_length / 2, 10, FONT_SIZE, Color::YELLOW);
+        d.draw_text(frame_text, screen_width / 2 - frame_text_length / 2, screen_height - 30, 20, Color::BROWN);
+
+        d.draw_fps(10, 10);
+        d.draw_text(&format!("Seed: {}", game.seed), 10, 30, 20, Color::DARKGRAY);
+
+        if game.game_over() {
+            let text = "GAME OVER";
+            let text_length = measure_text(text, FONT_SIZE);
+            d.draw_text(text, screen_width / 2 - text_length / 2, screen_width / 2 - text_length / 2, FONT_SIZE, Color::RED);
+        }
+        else if game.game_win() {
+            let text = "YOU WIN";
+            let text_length = measure_text(text, FONT_SIZE);
+            d.draw_text(text, screen_width / 2 - text_length / 2, screen_width / 2 - text_length / 2, FONT_SIZE, Color::GREEN);
+        }
+    }
+
+    // Every snake still in the round, with a scoreboard in its colours down the left side
+    pub fn draw_arena(d: &mut RaylibDrawHandle, arena: &Arena, colors: &Colors) {
+        let cell = cell_size(d, &arena.grid);
+
+        draw_walls(d, &arena.grid, cell, colors);
+        draw_food(d, &arena.food, cell, colors);
+
+        for (i, player) in arena.players.iter().enumerate() {
+            if !player.snake.game_over {
+                draw_snake(d, &player.snake, cell, &colors.for_snake(i));
+            }
+        }
+
+        draw_scoreboard(d, arena, colors);
+
+        let screen_width = d.get_screen_width();
+        let screen_height = d.get_screen_height();
+
+        let frame_text = &format!("Frames: {}", arena.frame_count);
+        let frame_text_length = measure_text(frame_text, 20);
+        d.draw_text(frame_text, screen_width / 2 - frame_text_length / 2, screen_height - 30, 20, Color::BROWN);
+
+        if arena.game_ended() {
+            let text = &match arena.winner() {
+                Some(i) => format!("SNAKE {} WINS THE ROUND", i + 1),
+                None => "DRAW".to_string(),
+            };
+            let text_length = measure_text(text, FONT_SIZE);
+            d.draw_text(text, screen_width / 2 - text_length / 2, screen_height / 2 - FONT_SIZE / 2, FONT_SIZE, Color::GREEN);
+        }
+    }
+
+    pub fn draw_scoreboard(d: &mut RaylibDrawHandle, arena: &Arena, colors: &Colors) {
+        for (i, player) in arena.players.iter().enumerate() {
+            let text = match player.out_at {
+                Some(at) => format!("{} {}: {}, {} wins (out at {})", i + 1, player.mode.name(), player.score, player.wins, at),
+                None => format!("{} {}: {}, {} wins", i + 1, player.mode.name(), player.score, player.wins),
+            };
+            let y = 10 + i as i32 * 22;
+
+            d.draw_rectangle(10, y, 18, 18, to_color(colors.for_snake(i).head));
+            d.draw_rectangle(28, y, 18, 18, to_color(colors.for_snake(i).body));
+            d.draw_text(&text, 52, y, 20, Color::DARKGRAY);
+        }
+    }
+
+    pub fn draw_replay_status(d: &mut RaylibDrawHandle, moves: usize, total: usize, paused: bool) {
+        let text = match paused {
+            true => format!("Move: {}/{} (paused)", moves, total),
+            false => format!("Move: {}/{}", moves, total),
+        };
+
+        d.draw_text(&text, 10, 50, 20, Color::DARKGRAY);
+    }
+}
@@ -0,0 +1,168 @@
+pub mod replay {
+    use std::fs;
+
+    use crate::game::game::*;
+    use crate::snake::snake::*;
+
+    // Everything needed to play a game back move for move. The food is stored as well as the seed,
+    // because strategies draw from the same random numbers and would move the food around otherwise.
+    #[derive(Clone)]
+    pub struct Replay {
+        pub grid: Grid,
+        pub seed: u64,
+        pub body: Vec<Pos>,
+        pub moves: Vec<Direction>,
+        // How many pieces of food there were at the start
+        pub pieces: usize,
+        // Where the food was at the start, followed by every place a piece respawned
+        pub food: Vec<Pos>,
+    }
+
+    impl Replay {
+        pub fn new(grid: Grid, seed: u64, body: Vec<Pos>, food: Vec<Pos>) -> Self {
+            Self {
+                grid,
+                seed,
+                body,
+                moves: Vec::new(),
+                pieces: food.len(),
+                food,
+            }
+        }
+
+        // Rebuild the game as it was after the given number of moves
+        pub fn play(&self, moves: usize) -> Game {
+            let mut game = Game::new(self.grid.clone(), self.body.clone(), Vec::new(), self.pieces, self.seed);
+            game.food.pieces = self.food[..self.pieces].to_vec();
+
+            for i in 0..moves.min(self.moves.len()) {
+                self.advance(&mut game, i);
+            }
+
+            game
+        }
+
+        // Make the move with this index in a game that has made all the moves before it
+        pub fn advance(&self, game: &mut Game, index: usize) {
+            let score = game.score;
+            let pieces = game.food.pieces.len();
+
+            game.set_next_direction(self.moves[index]);
+            let eaten = game.food_ahead();
+            game.step();
+
+            // Every piece eaten so far has respawned, pieces only run out once the board is full
+            if let Some(eaten) = eaten.filter(|_| game.score != score && game.food.pieces.len() == pieces) {
+                if let Some(pos) = self.food.get(self.pieces + game.score as usize - 1) {
+                    game.food.pieces[eaten] = *pos;
+                }
+            }
+        }
+
+        pub fn save(&self, path: &str) -> Result<(), String> {
+            fs::write(path, self.to_text()).map_err(|err| format!("Couldn't save the replay to '{}': {}", path, err))
+        }
+
+        pub fn load(path: &str) -> Result<Self, String> {
+            let text = fs::read_to_string(path).map_err(|err| format!("Couldn't read the replay '{}': {}", path, err))?;
+
+            Self::from_text(&text).map_err(|err| format!("Invalid replay '{}': {}", path, err))
+        }
+
+        fn to_text(&self) -> String {
+            let positions = |cells: &[Pos]| cells.iter().map(|pos| format!("{},{}", pos.x, pos.y)).collect::<Vec<_>>().join(" ");
+
+            let moves: String = self.moves.iter().map(|dir| match dir {
+                Direction::Up => 'U',
+                Direction::Down => 'D',
+                Direction::Left => 'L',
+                Direction::Right => 'R',
+            }).collect();
+
+            format!("snake replay\ngrid {}x{}\nwrap {}\nwalls {}\nseed {}\nbody {}\npieces {}\nfood {}\nmoves {}\n",
+                self.grid.width, self.grid.height, self.grid.wrap, positions(&self.grid.walls()), self.seed, positions(&self.body), self.pieces, positions(&self.food), moves)
+        }
+
+        fn from_text(text: &str) -> Result<Self, String> {
+            let mut lines = text.lines();
+            if lines.next() != Some("snake replay") {
+                return Err("missing the 'snake replay' header".to_string());
+            }
+
+            let mut grid = None;
+            let mut wrap = false;
+            let mut walls = Vec::new();
+            let mut seed = None;
+            let mut body = None;
+            let mut pieces = 1;
+            let mut food = None;
+            let mut moves = Vec::new();
+
+            for line in lines {
+                let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+
+                match key {
+                    "grid" => {
+                        let (width, height) = value.split_once('x').ok_or(format!("bad grid size '{}'", value))?;
+                        grid = Some(Grid::new(number(width)?, number(height)?));
+                    }
+                    "wrap" => wrap = value.parse().map_err(|_| format!("bad wrap '{}'", value))?,
+                    "walls" => walls = positions(value)?,
+                    "seed" => seed = Some(value.parse().map_err(|_| format!("bad seed '{}'", value))?),
+                    "body" => body = Some(positions(value)?),
+                    "pieces" => pieces = number(value)?,
+                    "food" => food = Some(positions(value)?),
+                    "moves" => {
+                        moves = value.chars().map(|c| match c {
+                            'U' => Ok(Direction::Up),
+                            'D' => Ok(Direction::Down),
+                            'L' => Ok(Direction::Left),
+                            'R' => Ok(Direction::Right),
+                            _ => Err(format!("bad move '{}'", c)),
+                        }).collect::<Result<_, _>>()?;
+                    }
+                    "" => {}
+                    _ => return Err(format!("unknown line '{}'", line)),
+                }
+            }
+
+            let grid: Grid = grid.ok_or("missing the grid size")?.with_wrap(wrap);
+            let body: Vec<Pos> = body.ok_or("missing the body")?;
+            let food: Vec<Pos> = food.ok_or("missing the food")?;
+
+            if body.len() < 2 || pieces == 0 || food.len() < pieces {
+                return Err(format!("the body needs at least two cells and there have to be {} pieces of food", pieces.max(1)));
+            }
+
+            if body.iter().chain(food.iter()).chain(walls.iter()).any(|pos| !grid.in_bounds(pos.x, pos.y)) {
+                return Err(format!("a position is outside of the {}x{} grid", grid.width, grid.height));
+            }
+
+            let grid = grid.with_walls(&walls);
+            if body.iter().chain(food.iter()).any(|pos| grid.is_wall(pos)) {
+                return Err("the snake or the food is inside a wall".to_string());
+            }
+
+            if body.windows(2).any(|pair| pair[1].get_dir_to(&pair[0], &grid).is_none()) {
+                return Err("the body isn't connected".to_string());
+            }
+
+            Ok(Self {
+                grid,
+                seed: seed.ok_or("missing the seed")?,
+                body,
+                moves,
+                pieces,
+                food,
+            })
+        }
+    }
+
+    fn number(text: &str) -> Result<usize, String> {
+        text.parse().map_err(|_| format!("bad number '{}'", text))
+    }
+
+    fn positions(text: &str) -> Result<Vec<Pos>, String> {
+        text.split_whitespace().map(|pos| pos.parse()).collect()
+    }
+}
@@ -0,0 +1,537 @@
+pub mod settings {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::snake::snake::*;
+    use crate::strategy::strategy::*;
+    use crate::{GRID_WIDTH, GRID_HEIGHT, CELL_SIZE, GAME_SPEED, FOOD_COUNT, ROUNDS};
+
+    pub type Rgb = (u8, u8, u8);
+
+    // The raylib colours, so they can be picked by name
+    const PALETTE: [(&str, Rgb); 25] = [
+        ("LIGHTGRAY", (200, 200, 200)),
+        ("GRAY", (130, 130, 130)),
+        ("DARKGRAY", (80, 80, 80)),
+        ("YELLOW", (253, 249, 0)),
+        ("GOLD", (255, 203, 0)),
+        ("ORANGE", (255, 161, 0)),
+        ("PINK", (255, 109, 194)),
+        ("RED", (230, 41, 55)),
+        ("MAROON", (190, 33, 55)),
+        ("GREEN", (0, 228, 48)),
+        ("LIME", (0, 158, 47)),
+        ("DARKGREEN", (0, 117, 44)),
+        ("SKYBLUE", (102, 191, 255)),
+        ("BLUE", (0, 121, 241)),
+        ("DARKBLUE", (0, 82, 172)),
+        ("PURPLE", (200, 122, 255)),
+        ("VIOLET", (135, 60, 190)),
+        ("DARKPURPLE", (112, 31, 126)),
+        ("BEIGE", (211, 176, 131)),
+        ("BROWN", (127, 106, 79)),
+        ("DARKBROWN", (76, 63, 47)),
+        ("WHITE", (255, 255, 255)),
+        ("BLACK", (0, 0, 0)),
+        ("MAGENTA", (255, 0, 255)),
+        ("RAYWHITE", (245, 245, 245)),
+    ];
+
+    // Raylib key codes, letters and digits use their ASCII codes
+    const ARROW_KEYS: [(&str, i32); 4] = [("RIGHT", 262), ("LEFT", 263), ("DOWN", 264), ("UP", 265)];
+
+    pub fn key_code(name: &str) -> Option<i32> {
+        if let Some((_, code)) = ARROW_KEYS.iter().find(|(arrow, _)| *arrow == name) {
+            return Some(*code);
+        }
+
+        match name.as_bytes() {
+            [c] if c.is_ascii_uppercase() || c.is_ascii_digit() => Some(*c as i32),
+            _ => None,
+        }
+    }
+
+    pub fn key_name(code: i32) -> Option<String> {
+        if let Some((name, _)) = ARROW_KEYS.iter().find(|(_, arrow)| *arrow == code) {
+            return Some(name.to_string());
+        }
+
+        match u8::try_from(code) {
+            Ok(c) if c.is_ascii_uppercase() || c.is_ascii_digit() => Some((c as char).to_string()),
+            _ => None,
+        }
+    }
+
+    #[derive(Clone, PartialEq, Debug)]
+    pub struct KeyBindings {
+        pub up: String,
+        pub down: String,
+        pub left: String,
+        pub right: String,
+    }
+
+    impl KeyBindings {
+        pub fn arrows() -> Self {
+            Self {
+                up: "UP".to_string(),
+                down: "DOWN".to_string(),
+                left: "LEFT".to_string(),
+                right: "RIGHT".to_string(),
+            }
+        }
+
+        // The second player on the same keyboard
+        pub fn wasd() -> Self {
+            Self {
+                up: "W".to_string(),
+                down: "S".to_string(),
+                left: "A".to_string(),
+                right: "D".to_string(),
+            }
+        }
+
+        pub fn get(&self, dir: Direction) -> &str {
+            match dir {
+                Direction::Up => &self.up,
+                Direction::Down => &self.down,
+                Direction::Left => &self.left,
+                Direction::Right => &self.right,
+            }
+        }
+
+        // The direction a key is bound to
+        pub fn direction(&self, name: &str) -> Option<Direction> {
+            [Direction::Up, Direction::Down, Direction::Left, Direction::Right].into_iter().find(|dir| self.get(*dir) == name)
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub struct Colors {
+        pub background: Rgb,
+        pub head: Rgb,
+        pub body: Rgb,
+        pub body_alt: Rgb,
+        pub food: Rgb,
+        pub wall: Rgb,
+        pub path: Rgb,
+    }
+
+    // The head, body and stripes of every snake after the first when several share the board
+    const SNAKE_COLORS: [(Rgb, Rgb, Rgb); 7] = [
+        ((0, 121, 241), (0, 82, 172), (102, 191, 255)),
+        ((255, 0, 255), (112, 31, 126), (135, 60, 190)),
+        ((253, 249, 0), (255, 161, 0), (255, 203, 0)),
+        ((211, 176, 131), (76, 63, 47), (127, 106, 79)),
+        ((255, 255, 255), (190, 33, 55), (255, 109, 194)),
+        ((255, 203, 0), (0, 0, 0), (50, 50, 50)),
+        ((0, 0, 0), (245, 245, 245), (255, 255, 255)),
+    ];
+
+    impl Colors {
+        // The first snake keeps the colours from the settings so it looks the same as in a game of its own
+        pub fn for_snake(&self, index: usize) -> Self {
+            match index {
+                0 => *self,
+                _ => {
+                    let (head, body, body_alt) = SNAKE_COLORS[(index - 1) % SNAKE_COLORS.len()];
+
+                    Self { head, body, body_alt, ..*self }
+                }
+            }
+        }
+    }
+
+    // Everything that can be changed from the settings screen, in the order it is shown
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub enum Field {
+        Width,
+        Height,
+        Wrap,
+        CellSize,
+        Speed,
+        FoodCount,
+        Rounds,
+        Mode,
+        Fallback,
+        Background,
+        Head,
+        Body,
+        BodyAlt,
+        Food,
+        Wall,
+        Path,
+        KeyUp,
+        KeyDown,
+        KeyLeft,
+        KeyRight,
+        Key2Up,
+        Key2Down,
+        Key2Left,
+        Key2Right,
+    }
+
+    impl Field {
+        pub const ALL: [Field; 24] = [
+            Field::Width, Field::Height, Field::Wrap, Field::CellSize, Field::Speed, Field::FoodCount, Field::Rounds, Field::Mode, Field::Fallback,
+            Field::Background, Field::Head, Field::Body, Field::BodyAlt, Field::Food, Field::Wall, Field::Path,
+            Field::KeyUp, Field::KeyDown, Field::KeyLeft, Field::KeyRight,
+            Field::Key2Up, Field::Key2Down, Field::Key2Left, Field::Key2Right,
+        ];
+
+        // The key in the settings file
+        pub fn name(&self) -> &'static str {
+            match self {
+                Field::Width => "width",
+                Field::Height => "height",
+                Field::Wrap => "wrap",
+                Field::CellSize => "cell_size",
+                Field::Speed => "speed",
+                Field::FoodCount => "food",
+                Field::Rounds => "rounds",
+                Field::Mode => "mode",
+                Field::Fallback => "fallback",
+                Field::Background => "background_color",
+                Field::Head => "head_color",
+                Field::Body => "body_color",
+                Field::BodyAlt => "body_alt_color",
+                Field::Food => "food_color",
+                Field::Wall => "wall_color",
+                Field::Path => "path_color",
+                Field::KeyUp => "key_up",
+                Field::KeyDown => "key_down",
+                Field::KeyLeft => "key_left",
+                Field::KeyRight => "key_right",
+                Field::Key2Up => "p2_key_up",
+                Field::Key2Down => "p2_key_down",
+                Field::Key2Left => "p2_key_left",
+                Field::Key2Right => "p2_key_right",
+            }
+        }
+
+        pub fn label(&self) -> &'static str {
+            match self {
+                Field::Width => "Width",
+                Field::Height => "Height",
+                Field::Wrap => "Wrap around",
+                Field::CellSize => "Cell size",
+                Field::Speed => "Speed",
+                Field::FoodCount => "Food pieces",
+                Field::Rounds => "Rounds to win",
+                Field::Mode => "Default mode",
+                Field::Fallback => "A* fallback",
+                Field::Background => "Background",
+                Field::Head => "Head",
+                Field::Body => "Body",
+                Field::BodyAlt => "Body stripes",
+                Field::Food => "Food",
+                Field::Wall => "Walls",
+                Field::Path => "Path",
+                Field::KeyUp => "Up key",
+                Field::KeyDown => "Down key",
+                Field::KeyLeft => "Left key",
+                Field::KeyRight => "Right key",
+                Field::Key2Up => "P2 up key",
+                Field::Key2Down => "P2 down key",
+                Field::Key2Left => "P2 left key",
+                Field::Key2Right => "P2 right key",
+            }
+        }
+
+        pub fn is_key(&self) -> bool {
+            matches!(self, Field::KeyUp | Field::KeyDown | Field::KeyLeft | Field::KeyRight |
+                           Field::Key2Up | Field::Key2Down | Field::Key2Left | Field::Key2Right)
+        }
+    }
+
+    #[derive(Clone, PartialEq, Debug)]
+    pub struct Settings {
+        pub width: usize,
+        pub height: usize,
+        pub wrap: bool,
+        pub cell_size: usize,
+        pub speed: usize,
+        // How many pieces of food are on the board at once
+        pub food: usize,
+        // Wins it takes to take a match between several snakes
+        pub rounds: usize,
+        // Used when no mode is given and there is no menu to pick one from
+        pub mode: Option<Mode>,
+        pub fallback: Fallback,
+        pub colors: Colors,
+        pub keys: KeyBindings,
+        pub keys2: KeyBindings,
+    }
+
+    impl Settings {
+        pub fn new() -> Self {
+            Self {
+                width: GRID_WIDTH,
+                height: GRID_HEIGHT,
+                wrap: false,
+                cell_size: CELL_SIZE,
+                speed: GAME_SPEED,
+                food: FOOD_COUNT,
+                rounds: ROUNDS,
+                mode: None,
+                fallback: Fallback::MostSpace,
+                colors: Colors {
+                    background: (200, 200, 200),
+                    head: (102, 191, 255),
+                    body: (0, 117, 44),
+                    body_alt: (0, 158, 47),
+                    food: (230, 41, 55),
+                    wall: (80, 80, 80),
+                    path: (130, 130, 130),
+                },
+                keys: KeyBindings::arrows(),
+                keys2: KeyBindings::wasd(),
+            }
+        }
+
+        // The keys of every player sharing the keyboard, in order
+        pub fn player_keys(&self) -> [&KeyBindings; 2] {
+            [&self.keys, &self.keys2]
+        }
+
+        // $XDG_CONFIG_HOME/rust_snake/settings.toml, or ~/.config when that isn't set
+        pub fn path() -> Option<PathBuf> {
+            let dir = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+                Some(dir) => PathBuf::from(dir),
+                None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+            };
+
+            Some(dir.join("rust_snake").join("settings.toml"))
+        }
+
+        // Missing files and settings fall back to the defaults
+        pub fn load() -> Result<Self, String> {
+            let path = match Self::path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::new()),
+            };
+
+            let text = fs::read_to_string(&path).map_err(|err| format!("Couldn't read {}: {}", path.display(), err))?;
+
+            Self::from_text(&text).map_err(|err| format!("Invalid settings in {}: {}", path.display(), err))
+        }
+
+        pub fn save(&self) -> Result<(), String> {
+            let path = Self::path().ok_or("Couldn't find the config directory, set $HOME or $XDG_CONFIG_HOME")?;
+
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|err| format!("Couldn't create {}: {}", dir.display(), err))?;
+            }
+
+            fs::write(&path, self.to_text()).map_err(|err| format!("Couldn't write {}: {}", path.display(), err))
+        }
+
+        // A small part of TOML: one `key = value` per line, with strings in quotes and # comments
+        pub fn from_text(text: &str) -> Result<Self, String> {
+            let mut settings = Self::new();
+
+            for (i, line) in text.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                let (key, value) = line.split_once('=').ok_or(format!("line {}: expected 'key = value'", i + 1))?;
+                let key = key.trim();
+                let value = parse_value(value.trim()).map_err(|err| format!("line {}: {}", i + 1, err))?;
+
+                let field = Field::ALL.iter().find(|field| field.name() == key)
+                    .ok_or(format!("line {}: unknown setting '{}'", i + 1, key))?;
+
+                settings.set(*field, &value).map_err(|err| format!("line {}: {}", i + 1, err))?;
+            }
+
+            Ok(settings)
+        }
+
+        pub fn to_text(&self) -> String {
+            let mut text = String::from("# Settings for rust_snake, the command line overrides these\n");
+
+            for field in Field::ALL.iter() {
+                let value = match field {
+                    Field::Width | Field::Height | Field::Wrap | Field::CellSize | Field::Speed | Field::FoodCount | Field::Rounds => self.value(*field),
+                    _ => format!("\"{}\"", self.value(*field)),
+                };
+
+                text += &format!("{} = {}\n", field.name(), value);
+            }
+
+            text
+        }
+
+        pub fn color(&self, field: Field) -> Option<Rgb> {
+            match field {
+                Field::Background => Some(self.colors.background),
+                Field::Head => Some(self.colors.head),
+                Field::Body => Some(self.colors.body),
+                Field::BodyAlt => Some(self.colors.body_alt),
+                Field::Food => Some(self.colors.food),
+                Field::Wall => Some(self.colors.wall),
+                Field::Path => Some(self.colors.path),
+                _ => None,
+            }
+        }
+
+        fn color_mut(&mut self, field: Field) -> Option<&mut Rgb> {
+            match field {
+                Field::Background => Some(&mut self.colors.background),
+                Field::Head => Some(&mut self.colors.head),
+                Field::Body => Some(&mut self.colors.body),
+                Field::BodyAlt => Some(&mut self.colors.body_alt),
+                Field::Food => Some(&mut self.colors.food),
+                Field::Wall => Some(&mut self.colors.wall),
+                Field::Path => Some(&mut self.colors.path),
+                _ => None,
+            }
+        }
+
+        fn key(&self, field: Field) -> Option<&str> {
+            match field {
+                Field::KeyUp => Some(&self.keys.up),
+                Field::KeyDown => Some(&self.keys.down),
+                Field::KeyLeft => Some(&self.keys.left),
+                Field::KeyRight => Some(&self.keys.right),
+                Field::Key2Up => Some(&self.keys2.up),
+                Field::Key2Down => Some(&self.keys2.down),
+                Field::Key2Left => Some(&self.keys2.left),
+                Field::Key2Right => Some(&self.keys2.right),
+                _ => None,
+            }
+        }
+
+        fn key_mut(&mut self, field: Field) -> Option<&mut String> {
+            match field {
+                Field::KeyUp => Some(&mut self.keys.up),
+                Field::KeyDown => Some(&mut self.keys.down),
+                Field::KeyLeft => Some(&mut self.keys.left),
+                Field::KeyRight => Some(&mut self.keys.right),
+                Field::Key2Up => Some(&mut self.keys2.up),
+                Field::Key2Down => Some(&mut self.keys2.down),
+                Field::Key2Left => Some(&mut self.keys2.left),
+                Field::Key2Right => Some(&mut self.keys2.right),
+                _ => None,
+            }
+        }
+
+        // The value as it is written in the settings file
+        pub fn value(&self, field: Field) -> String {
+            match field {
+                Field::Width => self.width.to_string(),
+                Field::Height => self.height.to_string(),
+                Field::Wrap => self.wrap.to_string(),
+                Field::CellSize => self.cell_size.to_string(),
+                Field::Speed => self.speed.to_string(),
+                Field::FoodCount => self.food.to_string(),
+                Field::Rounds => self.rounds.to_string(),
+                Field::Mode => self.mode.map(|mode| mode.name()).unwrap_or("menu").to_string(),
+                Field::Fallback => self.fallback.name().to_string(),
+                _ if field.is_key() => self.key(field).expect("Key fields have a key").to_string(),
+                _ => {
+                    let color = self.color(field).expect("The other fields are colours");
+
+                    match PALETTE.iter().find(|(_, rgb)| *rgb == color) {
+                        Some((name, _)) => name.to_string(),
+                        None => format!("#{:02X}{:02X}{:02X}", color.0, color.1, color.2),
+                    }
+                }
+            }
+        }
+
+        pub fn set(&mut self, field: Field, value: &str) -> Result<(), String> {
+            let number = || value.parse::<usize>().ok().filter(|n| *n > 0).ok_or(format!("'{}' isn't a positive number", value));
+
+            match field {
+                Field::Width => self.width = number()?.max(2),
+                Field::Height => self.height = number()?.max(2),
+                Field::Wrap => self.wrap = value.parse().map_err(|_| format!("'{}' isn't true or false", value))?,
+                Field::CellSize => self.cell_size = number()?,
+                Field::Speed => self.speed = number()?,
+                Field::FoodCount => self.food = number()?,
+                Field::Rounds => self.rounds = number()?,
+                Field::Mode => self.mode = match value {
+                    "menu" => None,
+                    _ => Some(value.parse()?),
+                },
+                Field::Fallback => self.fallback = value.parse()?,
+                _ if field.is_key() => {
+                    key_code(value).ok_or(format!("Unknown key '{}', expected a letter, a digit, UP, DOWN, LEFT or RIGHT", value))?;
+                    *self.key_mut(field).expect("Key fields have a key") = value.to_string();
+                }
+                _ => *self.color_mut(field).expect("The other fields are colours") = parse_color(value)?,
+            }
+
+            Ok(())
+        }
+
+        // Step to the next or previous value, used by the buttons on the settings screen
+        pub fn change(&mut self, field: Field, forward: bool) {
+            fn cycle<T: Copy + PartialEq>(all: &[T], cur: T, forward: bool) -> T {
+                let i = all.iter().position(|item| *item == cur).unwrap_or(0);
+
+                match forward {
+                    true => all[(i + 1) % all.len()],
+                    false => all[(i + all.len() - 1) % all.len()],
+                }
+            }
+
+            let step = |n: usize, min: usize| match forward {
+                true => n + 1,
+                false => n.saturating_sub(1).max(min),
+            };
+
+            match field {
+                Field::Width => self.width = step(self.width, 2),
+                Field::Height => self.height = step(self.height, 2),
+                Field::Wrap => self.wrap = !self.wrap,
+                Field::CellSize => self.cell_size = step(self.cell_size, 1),
+                Field::Speed => self.speed = step(self.speed, 1),
+                Field::FoodCount => self.food = step(self.food, 1),
+                Field::Rounds => self.rounds = step(self.rounds, 1),
+                Field::Mode => {
+                    let modes: Vec<Option<Mode>> = std::iter::once(None).chain(Mode::ALL.iter().copied().map(Some)).collect();
+                    self.mode = cycle(&modes, self.mode, forward);
+                }
+                Field::Fallback => self.fallback = cycle(&Fallback::ALL, self.fallback, forward),
+                _ if field.is_key() => {}
+                _ => {
+                    let colors: Vec<Rgb> = PALETTE.iter().map(|(_, rgb)| *rgb).collect();
+                    let color = self.color_mut(field).expect("The other fields are colours");
+                    *color = cycle(&colors, *color, forward);
+                }
+            }
+        }
+    }
+
+    fn parse_value(value: &str) -> Result<String, String> {
+        match value.strip_prefix('"') {
+            Some(rest) => {
+                let (string, rest) = rest.split_once('"').ok_or("missing the closing quote")?;
+
+                match rest.trim() {
+                    "" => Ok(string.to_string()),
+                    rest if rest.starts_with('#') => Ok(string.to_string()),
+                    rest => Err(format!("unexpected '{}' after the value", rest)),
+                }
+            }
+            None => Ok(value.split('#').next().unwrap_or("").trim().to_string()),
+        }
+    }
+
+    // A raylib colour name or #RRGGBB
+    fn parse_color(value: &str) -> Result<Rgb, String> {
+        if let Some((_, rgb)) = PALETTE.iter().find(|(name, _)| name.eq_ignore_ascii_case(value)) {
+            return Ok(*rgb);
+        }
+
+        let hex = value.strip_prefix('#').filter(|hex| hex.len() == 6 && hex.is_ascii())
+            .ok_or(format!("'{}' isn't a colour, use a name like DARKGREEN or #RRGGBB", value))?;
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("'{}' isn't a colour", value));
+
+        Ok((channel(0)?, channel(2)?, channel(4)?))
+    }
+}
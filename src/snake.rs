@@ -1,19 +1,69 @@
 pub mod snake {
     use std::ops::Sub;
-
-    use raylib::prelude::*;
-    use raylib::prelude::consts::KeyboardKey::*;
+    use std::str::FromStr;
 
     use rand::{*, seq::SliceRandom};
 
-    use crate::{CELL_SIZE, CELL_SIZE_I, GRID_WIDTH, GRID_HEIGHT, START_LEN};
-
-    pub fn in_bounds(x: usize, y: usize) -> bool {
-        x < GRID_WIDTH && y < GRID_HEIGHT
+    #[derive(Clone, PartialEq, Debug)]
+    pub struct Grid {
+        pub width: usize,
+        pub height: usize,
+        // Moving off one side comes back in on the other side
+        pub wrap: bool,
+        // One flag per cell, or empty when the board has no walls
+        walls: Vec<bool>
     }
 
-    pub fn in_bounds_i32(x: i32, y: i32) -> bool {
-        x >= 0 && x < GRID_WIDTH as i32 && y >= 0 && y < GRID_HEIGHT as i32
+    impl Grid {
+        pub fn new(width: usize, height: usize) -> Self {
+            Self {
+                width,
+                height,
+                wrap: false,
+                walls: Vec::new()
+            }
+        }
+
+        pub fn with_wrap(mut self, wrap: bool) -> Self {
+            self.wrap = wrap;
+            self
+        }
+
+        pub fn with_walls(mut self, walls: &[Pos]) -> Self {
+            self.walls = vec![false; self.width * self.height];
+            for pos in walls.iter() {
+                self.walls[pos.y * self.width + pos.x] = true;
+            }
+            self
+        }
+
+        pub fn set_wall(&mut self, pos: &Pos, wall: bool) {
+            if self.walls.is_empty() {
+                self.walls = vec![false; self.width * self.height];
+            }
+            self.walls[pos.y * self.width + pos.x] = wall;
+        }
+
+        pub fn is_wall(&self, pos: &Pos) -> bool {
+            !self.walls.is_empty() && self.walls[pos.y * self.width + pos.x]
+        }
+
+        pub fn walls(&self) -> Vec<Pos> {
+            (0..self.walls.len()).filter(|idx| self.walls[*idx]).map(|idx| Pos::new(idx % self.width, idx / self.width)).collect()
+        }
+
+        // How many cells the snake can move through
+        pub fn open_cells(&self) -> usize {
+            self.width * self.height - self.walls.iter().filter(|wall| **wall).count()
+        }
+
+        pub fn in_bounds(&self, x: usize, y: usize) -> bool {
+            x < self.width && y < self.height
+        }
+
+        pub fn in_bounds_i32(&self, x: i32, y: i32) -> bool {
+            x >= 0 && x < self.width as i32 && y >= 0 && y < self.height as i32
+        }
     }
 
     #[derive(Clone, Copy, PartialEq, Debug)]
@@ -30,22 +80,14 @@ pub mod snake {
             }
         }
 
-        pub fn get_neighbors(&self) -> Vec<Self> {
+        pub fn get_neighbors(&self, grid: &Grid) -> Vec<Self> {
             let mut vec = Vec::new();
-            if in_bounds_i32(self.x as i32 + 1, self.y as i32) {
-                vec.push(Self { x: self.x + 1, y: self.y })
-            }
-
-            if in_bounds_i32(self.x as i32 - 1, self.y as i32) {
-                vec.push(Self { x: self.x - 1, y: self.y })
-            }
 
-            if in_bounds_i32(self.x as i32, self.y as i32 + 1) {
-                vec.push(Self { x: self.x, y: self.y + 1 })
-            }
-
-            if in_bounds_i32(self.x as i32, self.y as i32 - 1) {
-                vec.push(Self { x: self.x, y: self.y - 1 })
+            for dir in [Direction::Right, Direction::Left, Direction::Down, Direction::Up] {
+                // On a grid that is two cells wide and wraps, left and right are the same cell
+                if let Some(pos) = self.adjacent(&dir, grid).filter(|pos| !vec.contains(pos)) {
+                    vec.push(pos);
+                }
             }
 
             vec
@@ -60,46 +102,58 @@ pub mod snake {
             }
         }
 
-        pub fn adjacent(&self, dir: &Direction) -> Option<Self> {
-            let x = self.x as i32;
-            let y = self.y as i32;
+        // The neighboring cell in that direction, which is on the other side of the grid when it wraps around.
+        // Walls count as being off the grid.
+        pub fn adjacent(&self, dir: &Direction, grid: &Grid) -> Option<Self> {
+            let (x, y) = match dir {
+                Direction::Up => (self.x as i32, self.y as i32 - 1),
+                Direction::Down => (self.x as i32, self.y as i32 + 1),
+                Direction::Left => (self.x as i32 - 1, self.y as i32),
+                Direction::Right => (self.x as i32 + 1, self.y as i32),
+            };
 
-            match dir {
-                Direction::Up => match in_bounds_i32(x, y - 1) {
-                    true => Some(Pos::new(self.x, (y - 1) as usize)),
-                    false => None,
-                },
-                Direction::Down => match in_bounds_i32(x, y + 1) {
-                    true => Some(Pos::new(self.x, (y + 1) as usize)),
-                    false => None,
-                },
-
-                Direction::Left => match in_bounds_i32(x - 1, y) {
-                    true => Some(Pos::new((x - 1) as usize, self.y)),
-                    false => None,
-                },
-                Direction::Right => match in_bounds_i32(x + 1, y) {
-                    true => Some(Pos::new((x + 1) as usize, self.y)),
-                    false => None,
-                },
-            }
-        }
-
-        pub fn get_dir_to(&self, other: &Pos) -> Option<Direction> {
-            let x: i32 = other.x as i32 - self.x as i32;
-            let y: i32 = other.y as i32 - self.y as i32;
-
-            match x {
-                0 => match y {
-                    0 => None,
-                    1 => Some(Direction::Down),
-                    -1 => Some(Direction::Up),
-                    _ => None
-                }
-                1 => if y == 0 { Some(Direction::Right) } else { None },
-                -1 => if y == 0 { Some(Direction::Left) } else { None },
-                _ => None
+            let pos = if grid.in_bounds_i32(x, y) {
+                Pos::new(x as usize, y as usize)
+            }
+            else if grid.wrap {
+                Pos::new(x.rem_euclid(grid.width as i32) as usize, y.rem_euclid(grid.height as i32) as usize)
             }
+            else {
+                return None;
+            };
+
+            match grid.is_wall(&pos) {
+                true => None,
+                false => Some(pos),
+            }
+        }
+
+        pub fn get_dir_to(&self, other: &Pos, grid: &Grid) -> Option<Direction> {
+            [Direction::Up, Direction::Down, Direction::Left, Direction::Right].into_iter()
+                .find(|dir| self.adjacent(dir, grid) == Some(*other))
+        }
+
+        // Steps between the cells when only moving along the grid
+        pub fn distance(&self, other: &Pos, grid: &Grid) -> usize {
+            let dx = self.x.abs_diff(other.x);
+            let dy = self.y.abs_diff(other.y);
+
+            match grid.wrap {
+                true => dx.min(grid.width - dx) + dy.min(grid.height - dy),
+                false => dx + dy,
+            }
+        }
+    }
+
+    // Written as x,y in replays and on the command line
+    impl FromStr for Pos {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let (x, y) = s.split_once(',').ok_or(format!("bad position '{}'", s))?;
+            let number = |text: &str| text.parse::<usize>().map_err(|_| format!("bad number '{}'", text));
+
+            Ok(Pos::new(number(x)?, number(y)?))
         }
     }
 
@@ -131,8 +185,16 @@ pub mod snake {
                 Direction::Right => Direction::Left,
             }
         }
+
+        pub fn perpendicular(&self) -> [Self; 2] {
+            match &self {
+                Direction::Up | Direction::Down => [Direction::Left, Direction::Right],
+                Direction::Left | Direction::Right => [Direction::Up, Direction::Down],
+            }
+        }
     }
 
+    #[derive(Clone)]
     pub struct Snake {
         pub body: Vec<Pos>,
         direction: Direction,
@@ -142,42 +204,35 @@ pub mod snake {
     }
 
     impl Snake {
-        pub fn new() -> Self {
-            let mut body = Vec::new();
-            for i in (0..START_LEN).rev() {
-                body.push(Pos::new(i % GRID_WIDTH, i / GRID_WIDTH));
-            }
+        // A snake that keeps heading the way its neck points
+        pub fn from_body(grid: &Grid, body: Vec<Pos>) -> Self {
+            let direction = body[1].get_dir_to(&body[0], grid).unwrap_or(Direction::Right);
 
             Self {
-                body: body,
-                direction: Direction::Right,
-                next_direction: Direction::Right,
+                body,
+                direction,
+                next_direction: direction,
                 game_over: false,
                 game_win: false,
             }
         }
 
-        pub fn reset(&mut self) {
-            let mut body = Vec::new();
-            for i in (0..START_LEN).rev() {
-                body.push(Pos::new(i % GRID_WIDTH, i / GRID_WIDTH));
-            }
-
-            self.body = body;
-            self.direction = Direction::Right;
-            self.next_direction = Direction::Right;
-            self.game_over = false;
-            self.game_win = false;
+        pub fn direction(&self) -> Direction {
+            self.direction
         }
 
-        pub fn len(&self) -> usize {
-            self.body.len()
+        pub fn next_direction(&self) -> Direction {
+            self.next_direction
         }
 
         pub fn head(&self) -> Pos {
             self.body[0]
         }
 
+        pub fn tail(&self) -> Pos {
+            self.body[self.body.len() - 1]
+        }
+
         pub fn exclude_head(&self) -> Vec<Pos> {
             self.body[1..].to_vec()
         }
@@ -198,61 +253,58 @@ pub mod snake {
             free
         }
 
-        pub fn get_random_free_dir(&mut self) -> Direction {
+        pub fn get_random_free_dir(&self, grid: &Grid, rng: &mut impl Rng) -> Direction {
             let head = self.head();
 
-            let neighbors = head.get_neighbors();
+            let neighbors = head.get_neighbors(grid);
             let free = self.get_free_spaces(neighbors);
 
-            if free.len() > 0 {
-                let mut rng = thread_rng();
-                let next = free.choose(&mut rng).expect("Failed to get a random position from free");
+            if !free.is_empty() {
+                let next = free.choose(rng).expect("Failed to get a random position from free");
                 
-                head.get_dir_to(&next).expect("Failed to get the direction to the next position")
+                head.get_dir_to(next, grid).expect("Failed to get the direction to the next position")
             }
             else {
                 Direction::Right
             }
         }
 
-        pub fn get_dir_of_free_space(&mut self) -> Direction {
+        pub fn get_dir_of_free_space(&self, grid: &Grid) -> Direction {
             let head = self.head();
 
-            let neighbors = head.get_neighbors();
+            let neighbors = head.get_neighbors(grid);
             let free = self.get_free_spaces(neighbors);
 
             if free.len() == 1 {
-                return head.get_dir_to(&free[0]).expect("Failed to get the direction to the next position");
+                return head.get_dir_to(&free[0], grid).expect("Failed to get the direction to the next position");
             }
 
             let mut max_space = 0;
-            let mut max_pos = Pos::new(GRID_WIDTH, GRID_HEIGHT);
+            let mut max_pos = None;
 
             for free_pos in free.iter() {
-                let space = self.get_space(free_pos);
+                let space = self.get_space(grid, free_pos);
 
                 if space > max_space {
-                    max_pos = *free_pos;
+                    max_pos = Some(*free_pos);
                     max_space = space;
                 }
             }
 
-            if max_pos.x == GRID_WIDTH || max_pos.y == GRID_HEIGHT {
-                Direction::Right
-            }
-            else {
-                head.get_dir_to(&max_pos).expect("Failed to get the direction to the pos with the largest open")
+            match max_pos {
+                Some(pos) => head.get_dir_to(&pos, grid).expect("Failed to get the direction to the pos with the largest open"),
+                None => Direction::Right,
             }
         }
 
-        pub fn get_space(&self, pos: &Pos) -> usize {
+        pub fn get_space(&self, grid: &Grid, pos: &Pos) -> usize {
             let mut open = Vec::new();
             open.push(*pos);
 
             let mut idx = 0;
             
             while idx < open.len() {
-                let neighbors = open[idx].get_neighbors();
+                let neighbors = open[idx].get_neighbors(grid);
                 let free = self.get_free_spaces(neighbors);
     
                 for free_pos in free.iter() {
@@ -267,38 +319,39 @@ pub mod snake {
             open.len()
         }
 
-        pub fn would_collide(&self) -> bool {
-            match self.head().adjacent(&self.direction) {
+        pub fn would_collide(&self, grid: &Grid) -> bool {
+            match self.head().adjacent(&self.direction, grid) {
                 Some(pos) => self.exclude_head().contains(&pos),
                 None => true,
             }
         }
 
-        pub fn get_inputs(&mut self, handle: &RaylibHandle) {
-            if      handle.is_key_down(KEY_UP)    && self.direction.opposite() != Direction::Up {
-                self.next_direction = Direction::Up;
-            }
-            else if handle.is_key_down(KEY_DOWN)  && self.direction.opposite() != Direction::Down {
-                self.next_direction = Direction::Down;
-            }
-            else if handle.is_key_down(KEY_LEFT)  && self.direction.opposite() != Direction::Left {
-                self.next_direction = Direction::Left;
-            }
-            else if handle.is_key_down(KEY_RIGHT) && self.direction.opposite() != Direction::Right {
-                self.next_direction = Direction::Right;
-            }
-        }
-
         pub fn set_next_direction(&mut self, dir: Direction) {
             if self.direction.opposite() != dir {
                 self.next_direction = dir;
             }
         }
 
-        fn eat_food(&mut self, food: &mut Food) -> bool{
-            if self.head() == food.pos {
-                self.game_win = food.respawn(&self);
-                let tmp = self.body[self.body.len() - 1].clone();
+        // Move the body along the path without checking for collisions, growing when the head reaches some food
+        pub fn follow(&mut self, grid: &Grid, food: &[Pos], path: &[Direction]) {
+            for dir in path {
+                let next = self.head().adjacent(dir, grid).expect("Failed to follow the path off the grid");
+                self.body.insert(0, next);
+                self.body.remove(self.body.len() - 1);
+
+                if food.contains(&next) {
+                    self.body.push(self.tail());
+                }
+
+                self.direction = *dir;
+                self.next_direction = *dir;
+            }
+        }
+
+        fn eat_food(&mut self, grid: &Grid, food: &mut Food, rng: &mut impl Rng) -> bool{
+            if let Some(index) = food.pieces.iter().position(|pos| *pos == self.head()) {
+                self.game_win = food.respawn(index, grid, &[self], rng);
+                let tmp = self.body[self.body.len() - 1];
                 self.body.push(tmp);
 
                 return true
@@ -307,79 +360,71 @@ pub mod snake {
             false
         }
 
-        fn collide(&mut self) {
+        fn collide(&mut self, grid: &Grid) {
             let head = self.head();
             if !self.game_over {
-                let pos = match head.adjacent(&self.next_direction) {
+                let pos = match head.adjacent(&self.next_direction, grid) {
                     Some(x) => x,
                     None => { self.game_over = true; return; }
                 };
 
                 match self.exclude_head().contains(&pos) {
                     true => self.game_over = true,
-                    false => self.game_over = !in_bounds(head.x, head.y)
+                    false => self.game_over = !grid.in_bounds(head.x, head.y)
                 }
             }
         }
 
-        pub fn update(&mut self, food: &mut Food, score: &mut i32) {
-            self.collide();
+        pub fn update(&mut self, grid: &Grid, food: &mut Food, score: &mut i32, rng: &mut impl Rng) {
+            self.collide(grid);
 
             if self.game_over {
                 return;
             }
 
-            self.direction = self.next_direction.clone();
-            let head = self.body.first().clone().expect("Failed to get the snake head");
+            self.direction = self.next_direction;
+            let head = self.body.first().expect("Failed to get the snake head");
 
-            let next = head.adjacent(&self.direction).expect("Failed to transform head to the next direction");
+            let next = head.adjacent(&self.direction, grid).expect("Failed to transform head to the next direction");
             self.body.insert(0, next);
             self.body.remove(self.body.len() - 1);
 
-            *score = match self.eat_food(food) {
+            *score = match self.eat_food(grid, food, rng) {
                 true => *score + 1,
                 false => *score,
             };
         }
-
-        pub fn draw(&self, draw: &mut RaylibDrawHandle) {
-            let mut color = Color::SKYBLUE;
-            for part in self.body.iter() {
-                let x = (part.x * CELL_SIZE) as i32;
-                let y = (part.y * CELL_SIZE) as i32;
-                
-                draw.draw_rectangle(x, y, CELL_SIZE_I, CELL_SIZE_I, color);
-
-                if color == Color::DARKGREEN {
-                    color = Color::LIME;
-                } else {
-                    color = Color::DARKGREEN;
-                }
-            }
-        }
     }
 
+    #[derive(Clone)]
     pub struct Food {
-        pub pos: Pos
+        // Every piece of food on the board, each one respawns on its own when it is eaten
+        pub pieces: Vec<Pos>,
+        // How many pieces there are at the start of a game
+        pub count: usize,
+        // The only places food spawns while one of them is free
+        pub spawns: Vec<Pos>
     }
 
     impl Food {
-        pub fn new() -> Self {
-            Self {
-                pos: Pos::new(
-                    get_random_value::<i32>(0, GRID_WIDTH as i32 - 1) as usize,
-                    get_random_value::<i32>(0, GRID_HEIGHT as i32- 1) as usize,
-                )
-            }
+        pub fn new(grid: &Grid, snakes: &[&Snake], count: usize, spawns: Vec<Pos>, rng: &mut impl Rng) -> Self {
+            let mut food = Self {
+                pieces: Vec::new(),
+                count,
+                spawns,
+            };
+
+            food.reset(grid, snakes, rng);
+            food
         }
 
-        fn get_free_spaces(&self, snake: &Snake) -> Vec<Pos> {
+        fn get_free_spaces(&self, grid: &Grid, snakes: &[&Snake]) -> Vec<Pos> {
             let mut spaces = Vec::new();
 
-            for x in 0..GRID_WIDTH {
-                for y in 0..GRID_HEIGHT {
+            for x in 0..grid.width {
+                for y in 0..grid.height {
                     let vector = Pos::new(x, y);
-                    if !snake.body.contains(&vector) {
+                    if !on_snake(snakes, &vector) && !grid.is_wall(&vector) && !self.pieces.contains(&vector) {
                         spaces.push(vector);
                     }
                 }
@@ -388,28 +433,66 @@ pub mod snake {
             spaces
         }
 
-        pub fn respawn(&mut self, snake: &Snake) -> bool {
-            self.pos = Pos::new(
-                get_random_value::<i32>(0, GRID_WIDTH as i32 - 1) as usize,
-                get_random_value::<i32>(0, GRID_HEIGHT as i32 - 1) as usize,
-            );
+        // A free spawn point, or any free cell when they are all taken
+        fn free_cell(&self, grid: &Grid, snakes: &[&Snake], rng: &mut impl Rng) -> Option<Pos> {
+            let spawns: Vec<Pos> = self.spawns.iter().copied().filter(|pos| !on_snake(snakes, pos) && !self.pieces.contains(pos)).collect();
+            if let Some(pos) = spawns.choose(rng) {
+                return Some(*pos);
+            }
 
-            let mut rng = thread_rng();
-            self.pos = match self.get_free_spaces(snake).choose(&mut rng) {
-                Some(x) => *x,
-                None => return true
-            };
+            self.get_free_spaces(grid, snakes).choose(rng).copied()
+        }
 
-            false
+        // Put every piece back on the board for a new game
+        pub fn reset(&mut self, grid: &Grid, snakes: &[&Snake], rng: &mut impl Rng) {
+            self.pieces.clear();
+
+            for _ in 0..self.count {
+                if let Some(pos) = self.free_cell(grid, snakes, rng) {
+                    self.pieces.push(pos);
+                }
+            }
         }
 
-        pub fn draw(&self, draw: &mut RaylibDrawHandle) {
-            let x = (self.pos.x * CELL_SIZE) as i32;
-            let y = (self.pos.y * CELL_SIZE) as i32;
-            draw.draw_rectangle(
-                x, y,
-                CELL_SIZE_I, CELL_SIZE_I,
-                Color::RED);
+        // Move the eaten piece somewhere free, it is gone for good when there is no room left.
+        // Returns whether the board has run out of food.
+        pub fn respawn(&mut self, index: usize, grid: &Grid, snakes: &[&Snake], rng: &mut impl Rng) -> bool {
+            match self.free_cell(grid, snakes, rng) {
+                Some(pos) => self.pieces[index] = pos,
+                None => {
+                    self.pieces.remove(index);
+                }
+            }
+
+            self.pieces.is_empty()
+        }
+    }
+
+    // Snakes that are out of the game have left the board
+    fn on_snake(snakes: &[&Snake], pos: &Pos) -> bool {
+        snakes.iter().any(|snake| !snake.game_over && snake.body.contains(pos))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        use super::*;
+
+        #[test]
+        fn food_is_never_placed_on_a_snake() {
+            let grid = Grid::new(4, 2);
+            let first = Snake::from_body(&grid, vec![Pos::new(2, 0), Pos::new(1, 0), Pos::new(0, 0)]);
+            let second = Snake::from_body(&grid, vec![Pos::new(1, 1), Pos::new(2, 1), Pos::new(3, 1)]);
+
+            for seed in 0..50 {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let food = Food::new(&grid, &[&first, &second], 2, vec![Pos::new(0, 0), Pos::new(3, 0)], &mut rng);
+
+                assert_eq!(food.pieces.len(), 2);
+                assert!(food.pieces.contains(&Pos::new(3, 0)));
+                assert!(food.pieces.contains(&Pos::new(0, 1)));
+            }
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,180 @@
+pub mod strategy {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use crate::astar::astar::*;
+    use crate::battlesnake::battlesnake::*;
+    use crate::game::game::*;
+    use crate::ham_cycle::ham_cycle::*;
+    use crate::longest_path::longest_path::*;
+    use crate::pipe::pipe::*;
+    use crate::snake::snake::*;
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub enum Mode {
+        Play,
+        AStar,
+        AStarSafe,
+        Hamiltonian,
+        HamiltonianShortcuts,
+        LongestPath,
+        // Another program steers the snake, the number picks which of the bots it is
+        Bot(usize)
+    }
+
+    impl Mode {
+        pub const ALL: [Mode; 6] = [
+            Mode::Play,
+            Mode::AStar,
+            Mode::AStarSafe,
+            Mode::Hamiltonian,
+            Mode::HamiltonianShortcuts,
+            Mode::LongestPath,
+        ];
+
+        // The name used on the command line and in the settings file
+        pub fn name(&self) -> &'static str {
+            match self {
+                Mode::Play => "play",
+                Mode::AStar => "astar",
+                Mode::AStarSafe => "astar-safe",
+                Mode::Hamiltonian => "ham",
+                Mode::HamiltonianShortcuts => "ham-short",
+                Mode::LongestPath => "longest",
+                Mode::Bot(_) => "bot",
+            }
+        }
+    }
+
+    impl FromStr for Mode {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            if s == "bot" {
+                return Ok(Mode::Bot(0));
+            }
+
+            Mode::ALL.iter().copied().find(|mode| mode.name() == s)
+                .ok_or(format!("Unknown mode '{}', expected play, astar, astar-safe, ham, ham-short, longest or bot", s))
+        }
+    }
+
+    // What AStar does when there is no path to the food
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub enum Fallback {
+        RandomDir,
+        MostSpace,
+        LongestPath
+    }
+
+    impl Fallback {
+        pub const ALL: [Fallback; 3] = [Fallback::RandomDir, Fallback::MostSpace, Fallback::LongestPath];
+
+        pub fn name(&self) -> &'static str {
+            match self {
+                Fallback::RandomDir => "random",
+                Fallback::MostSpace => "space",
+                Fallback::LongestPath => "longest",
+            }
+        }
+    }
+
+    impl FromStr for Fallback {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Fallback::ALL.iter().copied().find(|fallback| fallback.name() == s)
+                .ok_or(format!("Unknown fallback '{}', expected random, space or longest", s))
+        }
+    }
+
+    // Everything the strategies are set up with besides the mode
+    #[derive(Clone, Debug)]
+    pub struct StrategyOptions {
+        pub fallback: Fallback,
+        // Moves to wait before searching again when there was no path
+        pub search_every: usize,
+        // Where to find the bots, the first bot snake uses the first one and so on.
+        // An http:// address is a Battlesnake server, anything else a command to run.
+        pub bots: Vec<String>,
+        // How long a bot gets to answer before its snake keeps going the way it was
+        pub timeout: Duration,
+    }
+
+    pub fn create_strategy(mode: Mode, options: &StrategyOptions, game: &Game) -> Result<Box<dyn Strategy>, String> {
+        let mut strategy: Box<dyn Strategy> = match mode {
+            Mode::Play => Box::new(Human::new()),
+            Mode::AStar | Mode::AStarSafe => Box::new(AStar::new(options.fallback, mode == Mode::AStarSafe, options.search_every)),
+            Mode::LongestPath => Box::new(LongestPath::new(options.search_every)),
+            Mode::Bot(i) => {
+                let address = options.bots.get(i).ok_or(format!("There is no address for bot {}, pass one with --bot", i + 1))?;
+
+                match address.starts_with("http://") {
+                    true => Box::new(HttpBot::new(address, options.timeout)?),
+                    false => Box::new(PipeBot::new(address, options.timeout)?),
+                }
+            }
+            Mode::Hamiltonian | Mode::HamiltonianShortcuts => {
+                let mut ham = HamiltonianCycle::new(mode == Mode::HamiltonianShortcuts);
+                ham.generate(&game.grid, &game.snake, &mut *game.rng.borrow_mut())?;
+
+                Box::new(ham)
+            }
+        };
+
+        strategy.reset(game);
+
+        Ok(strategy)
+    }
+
+    pub trait Strategy {
+        // Pick the direction for the next step of the game
+        fn next_move(&mut self, game: &Game) -> Direction;
+
+        // Called whenever a new game has started
+        fn reset(&mut self, _game: &Game) {}
+
+        // Called once the snake is out or the game is won
+        fn end(&mut self, _game: &Game) {}
+
+        // Called for every direction key the player holds down
+        fn on_key(&mut self, _dir: Direction) {}
+
+        // The moves the strategy is planning to make, used for drawing
+        fn planned_path(&self) -> &[Direction] {
+            &[]
+        }
+    }
+
+    pub struct Human {
+        direction: Direction,
+        next_direction: Direction,
+    }
+
+    impl Human {
+        pub fn new() -> Self {
+            Self {
+                direction: Direction::Right,
+                next_direction: Direction::Right,
+            }
+        }
+    }
+
+    impl Strategy for Human {
+        fn next_move(&mut self, _game: &Game) -> Direction {
+            self.direction = self.next_direction;
+            self.direction
+        }
+
+        fn reset(&mut self, game: &Game) {
+            self.direction = game.snake.direction();
+            self.next_direction = self.direction;
+        }
+
+        fn on_key(&mut self, dir: Direction) {
+            if self.direction.opposite() != dir {
+                self.next_direction = dir;
+            }
+        }
+    }
+}
@@ -0,0 +1,260 @@
+pub mod terminal {
+    use std::io::{self, Read, Write};
+    use std::process::Command;
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::game::game::*;
+    use crate::settings::settings::*;
+    use crate::snake::snake::*;
+    use crate::strategy::strategy::*;
+    use crate::FPS;
+
+    #[derive(PartialEq, Debug)]
+    enum Key {
+        Dir(Direction),
+        Reset,
+        Quit,
+    }
+
+    // Puts the terminal into a mode where keys arrive as they are pressed and puts it back when dropped
+    struct RawMode {
+        saved: String,
+    }
+
+    impl RawMode {
+        fn enable() -> Result<Self, String> {
+            let saved = stty(&["-g"])?;
+            stty(&["-icanon", "-echo", "-isig", "min", "1"])?;
+
+            print!("\x1b[?25l\x1b[2J");
+
+            Ok(Self { saved: saved.trim().to_string() })
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            println!("\x1b[0m\x1b[?25h");
+            let _ = io::stdout().flush();
+            let _ = stty(&[&self.saved]);
+        }
+    }
+
+    fn stty(args: &[&str]) -> Result<String, String> {
+        let output = Command::new("stty")
+            .args(args)
+            .stdin(std::process::Stdio::inherit())
+            .output()
+            .map_err(|err| format!("Couldn't run stty: {}", err))?;
+
+        match output.status.success() {
+            true => Ok(String::from_utf8_lossy(&output.stdout).to_string()),
+            false => Err("The terminal renderer needs to run in a terminal".to_string()),
+        }
+    }
+
+    // Stdin blocks, so it gets read on its own thread
+    fn spawn_input() -> Receiver<u8> {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut stdin = io::stdin();
+            let mut buffer = [0; 16];
+
+            while let Ok(read) = stdin.read(&mut buffer) {
+                if read == 0 || buffer[..read].iter().any(|byte| sender.send(*byte).is_err()) {
+                    break;
+                }
+            }
+        });
+
+        receiver
+    }
+
+    // Arrow keys come in as escape sequences like ESC [ A, everything else as the letter that was typed
+    fn get_inputs(input: &Receiver<u8>, pending: &mut Vec<u8>, keys: &KeyBindings) -> Vec<Key> {
+        pending.extend(input.try_iter());
+
+        let mut pressed = Vec::new();
+        let mut i = 0;
+
+        while i < pending.len() {
+            match pending[i] {
+                b'\x1b' if i + 2 < pending.len() && pending[i + 1] == b'[' => {
+                    let name = match pending[i + 2] {
+                        b'A' => "UP",
+                        b'B' => "DOWN",
+                        b'C' => "RIGHT",
+                        b'D' => "LEFT",
+                        _ => "",
+                    };
+
+                    if let Some(dir) = keys.direction(name) {
+                        pressed.push(Key::Dir(dir));
+                    }
+                    i += 3;
+                }
+                // Wait for the rest of the sequence
+                b'\x1b' if i + 2 == pending.len() && pending[i + 1] == b'[' => break,
+                // Escape pressed on its own, which does nothing
+                b'\x1b' => i += 1,
+                byte => {
+                    let name = (byte.to_ascii_uppercase() as char).to_string();
+
+                    match keys.direction(&name) {
+                        Some(dir) => pressed.push(Key::Dir(dir)),
+                        None if name == "R" => pressed.push(Key::Reset),
+                        None if name == "Q" || byte == 3 => pressed.push(Key::Quit),
+                        None => {}
+                    }
+                    i += 1;
+                }
+            }
+        }
+
+        pending.drain(..i);
+        pressed
+    }
+
+    fn draw(game: &Game, path: &[Direction], colors: &Colors) -> String {
+        let grid = &game.grid;
+        let mut cells = vec![colors.background; grid.width * grid.height];
+
+        let mut cur = game.snake.head();
+        for dir in path.iter() {
+            cur = cur.adjacent(dir, grid).expect("The path stays on the grid");
+            cells[cur.y * grid.width + cur.x] = colors.path;
+        }
+
+        for wall in grid.walls().iter() {
+            cells[wall.y * grid.width + wall.x] = colors.wall;
+        }
+
+        for pos in game.food.pieces.iter() {
+            cells[pos.y * grid.width + pos.x] = colors.food;
+        }
+
+        for (i, part) in game.snake.body.iter().enumerate() {
+            cells[part.y * grid.width + part.x] = match i {
+                0 => colors.head,
+                _ if i % 2 == 1 => colors.body,
+                _ => colors.body_alt,
+            };
+        }
+
+        // Only switch colours when they change, a full escape code for every cell is a lot to send over SSH
+        let mut out = String::from("\x1b[H");
+        for row in cells.chunks(grid.width) {
+            let mut last = None;
+
+            for &(r, g, b) in row.iter() {
+                if last != Some((r, g, b)) {
+                    out += &format!("\x1b[48;2;{};{};{}m", r, g, b);
+                    last = Some((r, g, b));
+                }
+                out += "  ";
+            }
+            out += "\x1b[0m\x1b[K\n";
+        }
+
+        out += &format!("Score: {}   Frames: {}   Seed: {}\x1b[K\n", game.score, game.frame_count, game.seed);
+
+        if game.game_over() {
+            out += "\x1b[1;31mGAME OVER\x1b[0m, r to restart, q to quit\x1b[K\n";
+        }
+        else if game.game_win() {
+            out += "\x1b[1;32mYOU WIN\x1b[0m, r to restart, q to quit\x1b[K\n";
+        }
+        else {
+            out += "\x1b[K\n";
+        }
+
+        out
+    }
+
+    // The same loop as the raylib window, drawn with ANSI colours instead
+    pub fn run(game: &mut Game, strategy: &mut dyn Strategy, game_speed: usize, settings: &Settings) -> Result<(), String> {
+        let _raw = RawMode::enable()?;
+        let input = spawn_input();
+        let mut pending = Vec::new();
+
+        let mut frames = 0;
+        let mut redraw = true;
+
+        loop {
+            for key in get_inputs(&input, &mut pending, &settings.keys) {
+                match key {
+                    Key::Dir(dir) => strategy.on_key(dir),
+                    Key::Reset => {
+                        game.reset();
+                        strategy.reset(game);
+                        frames = 0;
+                        redraw = true;
+                    }
+                    Key::Quit => return Ok(()),
+                }
+            }
+
+            if frames % game_speed == 0 && !game.game_ended() {
+                game.step_with(strategy);
+                redraw = true;
+            }
+
+            frames += 1;
+
+            // The terminal keeps what was drawn, so only draw again when something changed
+            if redraw {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(draw(game, strategy.planned_path(), &settings.colors).as_bytes()).map_err(|err| err.to_string())?;
+                stdout.flush().map_err(|err| err.to_string())?;
+                redraw = false;
+            }
+
+            thread::sleep(Duration::from_secs(1) / FPS);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn keys_for(bytes: &[u8], pending: &mut Vec<u8>) -> Vec<Key> {
+            let (sender, receiver) = mpsc::channel();
+            bytes.iter().for_each(|byte| sender.send(*byte).unwrap());
+
+            get_inputs(&receiver, pending, &KeyBindings::arrows())
+        }
+
+        #[test]
+        fn arrow_keys_and_letters() {
+            let mut pending = Vec::new();
+
+            assert_eq!(keys_for(b"\x1b[Ar\x1b[Dq", &mut pending), vec![Key::Dir(Direction::Up), Key::Reset, Key::Dir(Direction::Left), Key::Quit]);
+            assert!(pending.is_empty());
+        }
+
+        #[test]
+        fn a_lone_escape_is_dropped() {
+            let mut pending = Vec::new();
+
+            assert_eq!(keys_for(b"\x1b", &mut pending), vec![]);
+            assert!(pending.is_empty());
+            assert_eq!(keys_for(b"q", &mut pending), vec![Key::Quit]);
+
+            assert_eq!(keys_for(b"\x1br\x1b", &mut pending), vec![Key::Reset]);
+            assert!(pending.is_empty());
+        }
+
+        #[test]
+        fn a_split_sequence_waits_for_the_rest() {
+            let mut pending = Vec::new();
+
+            assert_eq!(keys_for(b"r\x1b[", &mut pending), vec![Key::Reset]);
+            assert_eq!(pending, b"\x1b[");
+            assert_eq!(keys_for(b"B", &mut pending), vec![Key::Dir(Direction::Down)]);
+            assert!(pending.is_empty());
+        }
+    }
+}